@@ -2,4 +2,6 @@ pub mod alias;
 pub mod api_dep;
 pub mod call_graph;
 pub mod dataflow;
+pub mod graph;
 pub mod heap_item;
+pub mod summary;
@@ -12,6 +12,8 @@ use rustc_middle::ty::TyCtxt;
 pub struct CallGraph<'tcx> {
     pub tcx: TyCtxt<'tcx>,
     pub graph: CallGraphInfo,
+    pub virtual_calls: bool, // resolve the virtual calls to all the implementors, which is an over-approximation
+    pub skip_sysroot_calls: bool, // only keep the calls to the functions outside the toolchain
 }
 
 impl<'tcx> CallGraph<'tcx> {
@@ -19,6 +21,8 @@ impl<'tcx> CallGraph<'tcx> {
         Self {
             tcx: tcx,
             graph: CallGraphInfo::new(),
+            virtual_calls: false,
+            skip_sysroot_calls: false,
         }
     }
 
@@ -38,6 +42,8 @@ impl<'tcx> CallGraph<'tcx> {
                     };
                     let mut call_graph_visitor =
                         CallGraphVisitor::new(self.tcx, def_id.into(), body, &mut self.graph);
+                    call_graph_visitor.virtual_calls = self.virtual_calls;
+                    call_graph_visitor.skip_sysroot_calls = self.skip_sysroot_calls;
                     call_graph_visitor.visit();
                 }
             }
@@ -57,3 +63,28 @@ impl<'tcx> CallGraph<'tcx> {
         self.graph.get_callees_path(&def_path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{capture_logs, captured_logs, with_tcx};
+    use log::Level;
+
+    #[test]
+    fn test_recursion_is_logged() {
+        let source = r#"
+            pub fn countdown(n: u32) -> u32 {
+                if n == 0 { 0 } else { countdown(n - 1) }
+            }
+        "#;
+        capture_logs();
+        with_tcx("recursion_log", source, |tcx| {
+            CallGraph::new(tcx).start();
+        });
+        let warning = (
+            Level::Warn,
+            String::from("Find a recursion function which may cause stackoverflow: countdown"),
+        );
+        assert!(captured_logs().contains(&warning));
+    }
+}
@@ -1,8 +1,10 @@
 use rustc_hir::def_id::DefId;
+use serde_json::{json, Value};
 use std::collections::HashSet;
 use std::{collections::HashMap, hash::Hash};
 
-use crate::rap_info;
+use crate::analysis::core::graph::{strongly_connected_components, DirectedGraph, Direction};
+use crate::{rap_debug, rap_info};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Node {
@@ -27,11 +29,30 @@ impl Node {
     }
 }
 
+// The condensation of a call graph, where each strongly connected component becomes one node.
+// The components are in topological order, i.e., the callers precede the callees,
+// so every edge goes from a smaller index to a larger one and the condensation is always acyclic.
+pub struct Condensation {
+    pub components: Vec<Vec<usize>>, // component idx -> the ids of its functions in ascending order
+    pub component_of: HashMap<usize, usize>, // function id -> component idx
+    pub edges: HashMap<usize, HashSet<usize>>, // component idx -> the indices of the called components
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CallGraphMetrics {
+    pub function_count: usize,
+    pub edge_count: usize, //the number of distinct (caller, callee) pairs
+    pub recursive_count: usize,
+    pub max_out_degree: usize, //the most callees of one function
+    pub max_in_degree: usize,  //the most callers of one function
+}
+
 pub struct CallGraphInfo {
     pub functions: HashMap<usize, Node>, // id -> node
     // pub function_calls: Vec<(usize, usize)>,   // (id, id)
     pub function_calls: HashMap<usize, HashSet<usize>>,
     pub node_registry: HashMap<String, usize>, // path -> id
+    pub call_counts: HashMap<(usize, usize), usize>, // (id, id) -> the number of call sites
 }
 
 impl CallGraphInfo {
@@ -40,6 +61,7 @@ impl CallGraphInfo {
             functions: HashMap::new(),
             function_calls: HashMap::new(),
             node_registry: HashMap::new(),
+            call_counts: HashMap::new(),
         }
     }
 
@@ -47,6 +69,26 @@ impl CallGraphInfo {
         self.functions.len()
     }
 
+    // A one-shot summary of the call graph, where the degrees are computed in one pass over the calls.
+    pub fn metrics(&self) -> CallGraphMetrics {
+        let mut metrics = CallGraphMetrics {
+            function_count: self.functions.len(),
+            recursive_count: self.recursive_function_ids().len(),
+            ..Default::default()
+        };
+        let mut in_degrees: HashMap<usize, usize> = HashMap::new();
+        for (_, callees) in self.function_calls.iter() {
+            metrics.edge_count += callees.len();
+            metrics.max_out_degree = metrics.max_out_degree.max(callees.len());
+            for callee_id in callees {
+                let in_degree = in_degrees.entry(*callee_id).or_insert(0);
+                *in_degree += 1;
+                metrics.max_in_degree = metrics.max_in_degree.max(*in_degree);
+            }
+        }
+        metrics
+    }
+
     pub fn get_callees_path(&self, caller_def_path: &String) -> Option<HashSet<String>> {
         let mut callees_path: HashSet<String> = HashSet::new();
         if let Some(caller_id) = self.node_registry.get(caller_def_path) {
@@ -79,6 +121,165 @@ impl CallGraphInfo {
         if let Some(callees) = self.function_calls.get_mut(&caller_id) {
             callees.insert(callee_id);
         }
+        *self.call_counts.entry((caller_id, callee_id)).or_insert(0) += 1;
+    }
+
+    // Merge another call graph (e.g., built for another crate) into this one.
+    // Nodes are unified by their def paths, so the ids of `other` are remapped to the ids of `self`.
+    pub fn merge(&mut self, other: CallGraphInfo) {
+        let mut id_map: HashMap<usize, usize> = HashMap::new();
+        for (other_id, node) in other.functions.iter() {
+            let def_path = node.get_def_path();
+            self.add_node(node.get_def_id(), &def_path);
+            if let Some(id) = self.get_noed_by_path(&def_path) {
+                id_map.insert(*other_id, id);
+            }
+        }
+        for (other_caller_id, callees) in other.function_calls.iter() {
+            if let Some(&caller_id) = id_map.get(other_caller_id) {
+                for other_callee_id in callees {
+                    if let Some(&callee_id) = id_map.get(other_callee_id) {
+                        self.add_funciton_call_edge(caller_id, callee_id);
+                        // the call sites of both graphs are counted
+                        if let Some(count) =
+                            other.call_counts.get(&(*other_caller_id, *other_callee_id))
+                        {
+                            *self.call_counts.entry((caller_id, callee_id)).or_insert(0) +=
+                                count - 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Collect the functions lying on any cycle of the call graph, i.e., the functions that can recurse.
+    // Besides the self-recursive functions, it also covers the mutually recursive ones,
+    // which are the members of the non-trivial strongly connected components (computed by Tarjan's algorithm).
+    pub fn recursive_functions(&self) -> Vec<DefId> {
+        self.recursive_function_ids()
+            .iter()
+            .filter_map(|id| self.functions.get(id).map(|node| node.get_def_id()))
+            .collect()
+    }
+
+    // the ids of the recursive functions in ascending order
+    fn recursive_function_ids(&self) -> Vec<usize> {
+        let mut recursive: Vec<usize> = strongly_connected_components(self)
+            .into_iter()
+            .filter(|scc| {
+                scc.len() > 1
+                    || self
+                        .function_calls
+                        .get(&scc[0])
+                        .map_or(false, |callees| callees.contains(&scc[0]))
+            })
+            .flatten()
+            .collect();
+        recursive.sort_unstable();
+        recursive
+    }
+
+    // Collapse each strongly connected component (e.g., a group of mutually recursive functions) into one node,
+    // so that even recursive programs can be ordered topologically at the component level.
+    // The calls within a component are dropped.
+    pub fn strongly_connected_condensation(&self) -> Condensation {
+        let mut components = strongly_connected_components(self);
+        // Tarjan's algorithm emits the callees before the callers
+        components.reverse();
+        let mut component_of = HashMap::new();
+        for (idx, component) in components.iter_mut().enumerate() {
+            component.sort_unstable();
+            for id in component.iter() {
+                component_of.insert(*id, idx);
+            }
+        }
+        let mut edges: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (caller_id, callees) in self.function_calls.iter() {
+            let Some(&from) = component_of.get(caller_id) else {
+                continue;
+            };
+            for callee_id in callees {
+                if let Some(&to) = component_of.get(callee_id) {
+                    if from != to {
+                        edges.entry(from).or_default().insert(to);
+                    }
+                }
+            }
+        }
+        Condensation {
+            components,
+            component_of,
+            edges,
+        }
+    }
+
+    // Export the call graph as a JSON object:
+    // {
+    //   "nodes": [{"id": 0, "def_path": "..", "recursive": false}],
+    //   "edges": [{"from": 0, "to": 1, "count": 1}]
+    // }
+    // The "count" of an edge is the number of call sites from the caller to the callee.
+    pub fn to_json(&self) -> String {
+        let recursive_ids: HashSet<usize> = self.recursive_function_ids().into_iter().collect();
+        let mut ids: Vec<&usize> = self.functions.keys().collect();
+        ids.sort_unstable();
+        let nodes: Vec<Value> = ids
+            .iter()
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "def_path": self.functions[id].get_def_path(),
+                    "recursive": recursive_ids.contains(id),
+                })
+            })
+            .collect();
+        let mut calls: Vec<(&(usize, usize), &usize)> = self.call_counts.iter().collect();
+        calls.sort_unstable();
+        let edges: Vec<Value> = calls
+            .iter()
+            .map(|((caller_id, callee_id), count)| {
+                json!({
+                    "from": caller_id,
+                    "to": callee_id,
+                    "count": count,
+                })
+            })
+            .collect();
+        json!({
+            "nodes": nodes,
+            "edges": edges,
+        })
+        .to_string()
+    }
+
+    // Export the call graph as a plain edge list for the external graph tools:
+    //     # 3 nodes, 2 edges
+    //     # 0 main
+    //     # 1 foo
+    //     # 2 bar
+    //     0 1
+    //     1 2
+    // The comments map the ids to the def paths, and each edge is a (caller, callee) pair sorted by ids.
+    pub fn to_edge_list(&self) -> String {
+        let mut ids: Vec<&usize> = self.functions.keys().collect();
+        ids.sort_unstable();
+        let mut calls: Vec<(usize, usize)> = self
+            .function_calls
+            .iter()
+            .flat_map(|(caller_id, callees)| {
+                callees.iter().map(|callee_id| (*caller_id, *callee_id))
+            })
+            .collect();
+        calls.sort_unstable();
+        let mut edge_list = format!("# {} nodes, {} edges\n", ids.len(), calls.len());
+        for id in ids {
+            edge_list.push_str(&format!("# {} {}\n", id, self.functions[id].get_def_path()));
+        }
+        for (caller_id, callee_id) in calls {
+            edge_list.push_str(&format!("{} {}\n", caller_id, callee_id));
+        }
+        edge_list
     }
 
     pub fn get_noed_by_path(&self, def_path: &String) -> Option<usize> {
@@ -91,7 +292,7 @@ impl CallGraphInfo {
 
     pub fn print_call_graph(&self) {
         rap_info!("CallGraph Analysis:");
-        // println!("There are {} functions calls!", self.function_calls.len());
+        rap_debug!("There are {} functions calls!", self.function_calls.len());
         for (caller_id, callees) in self.function_calls.clone() {
             if let Some(caller_node) = self.functions.get(&caller_id) {
                 for callee_id in callees {
@@ -115,3 +316,200 @@ impl CallGraphInfo {
         // }
     }
 }
+
+impl DirectedGraph for CallGraphInfo {
+    type Node = usize;
+    type Edge = (usize, usize); //the (caller, callee) pair
+
+    fn nodes(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.functions.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn edges(&self, node: usize, direction: Direction) -> Vec<((usize, usize), usize)> {
+        call_edges(&self.function_calls, node, direction)
+    }
+}
+
+impl DirectedGraph for Condensation {
+    type Node = usize;
+    type Edge = (usize, usize); //the (caller, callee) pair of components
+
+    fn nodes(&self) -> Vec<usize> {
+        (0..self.components.len()).collect()
+    }
+
+    fn edges(&self, node: usize, direction: Direction) -> Vec<((usize, usize), usize)> {
+        call_edges(&self.edges, node, direction)
+    }
+}
+
+// the callers and the callees of the node in ascending order, with the callers first for `Direction::Both`
+fn call_edges(
+    calls: &HashMap<usize, HashSet<usize>>,
+    node: usize,
+    direction: Direction,
+) -> Vec<((usize, usize), usize)> {
+    let mut callers: Vec<usize> = calls
+        .iter()
+        .filter(|(_, callees)| callees.contains(&node))
+        .map(|(caller, _)| *caller)
+        .collect();
+    callers.sort_unstable();
+    let mut callees: Vec<usize> = match calls.get(&node) {
+        Some(callees) => callees.iter().copied().collect(),
+        None => vec![],
+    };
+    callees.sort_unstable();
+    let in_edges = callers.into_iter().map(|caller| ((caller, node), caller));
+    let out_edges = callees.into_iter().map(|callee| ((node, callee), callee));
+    match direction {
+        Direction::Upside => in_edges.collect(),
+        Direction::Downside => out_edges.collect(),
+        Direction::Both => in_edges.chain(out_edges).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hir::def_id::CRATE_DEF_ID;
+
+    // a call graph of the def paths alone, whose ids follow the order of the paths
+    fn call_graph(def_paths: &[&str], calls: &[(&str, &str)]) -> CallGraphInfo {
+        let mut call_graph = CallGraphInfo::new();
+        for def_path in def_paths {
+            call_graph.add_node(CRATE_DEF_ID.to_def_id(), &def_path.to_string());
+        }
+        for (caller, callee) in calls {
+            let caller_id = call_graph.get_noed_by_path(&caller.to_string()).unwrap();
+            let callee_id = call_graph.get_noed_by_path(&callee.to_string()).unwrap();
+            call_graph.add_funciton_call_edge(caller_id, callee_id);
+        }
+        call_graph
+    }
+    #[test]
+    fn test_merge_unifies_def_paths() {
+        // the ids of `other` differ from those of `call_graph`
+        let other = call_graph(&["bar", "foo", "main"], &[("main", "foo"), ("foo", "bar")]);
+        let mut call_graph = call_graph(&["main", "foo"], &[("main", "foo")]);
+        call_graph.merge(other);
+        assert_eq!(call_graph.get_node_num(), 3);
+        assert_eq!(call_graph.get_noed_by_path(&String::from("bar")), Some(2));
+        assert_eq!(call_graph.function_calls[&0], HashSet::from([1]));
+        assert_eq!(call_graph.function_calls[&1], HashSet::from([2]));
+        assert_eq!(call_graph.call_counts[&(0, 1)], 2);
+        assert_eq!(call_graph.call_counts[&(1, 2)], 1);
+    }
+
+    #[test]
+    fn test_condensation_of_mutual_recursion() {
+        // main -> even <-> odd -> print
+        let call_graph = call_graph(
+            &["main", "even", "odd", "print"],
+            &[
+                ("main", "even"),
+                ("even", "odd"),
+                ("odd", "even"),
+                ("odd", "print"),
+            ],
+        );
+        let condensation = call_graph.strongly_connected_condensation();
+        assert_eq!(condensation.components, vec![vec![0], vec![1, 2], vec![3]]);
+        assert_eq!(condensation.component_of[&1], condensation.component_of[&2]);
+        // the calls between even and odd are dropped
+        assert_eq!(condensation.edges[&0], HashSet::from([1]));
+        assert_eq!(condensation.edges[&1], HashSet::from([2]));
+        assert!(!condensation.edges.contains_key(&2));
+    }
+
+    #[test]
+    fn test_to_edge_list() {
+        let call_graph = call_graph(
+            &["main", "foo", "bar"],
+            &[("foo", "bar"), ("main", "foo"), ("main", "foo")],
+        );
+        assert_eq!(
+            call_graph.to_edge_list(),
+            "# 3 nodes, 2 edges\n# 0 main\n# 1 foo\n# 2 bar\n0 1\n1 2\n"
+        );
+    }
+
+    #[test]
+    fn test_recursive_functions() {
+        // main -> fact -> fact, main -> even <-> odd, main -> print
+        let recursive = call_graph(
+            &["main", "fact", "even", "odd", "print"],
+            &[
+                ("main", "fact"),
+                ("fact", "fact"),
+                ("main", "even"),
+                ("even", "odd"),
+                ("odd", "even"),
+                ("main", "print"),
+            ],
+        );
+        assert_eq!(recursive.recursive_function_ids(), vec![1, 2, 3]);
+        assert_eq!(recursive.recursive_functions().len(), 3);
+        let acyclic = call_graph(&["main", "print"], &[("main", "print")]);
+        assert!(acyclic.recursive_functions().is_empty());
+    }
+
+    #[test]
+    fn test_to_json_counts_call_sites() {
+        // main -> fact twice, fact -> fact
+        let mut recursive = call_graph(&["main", "fact"], &[("main", "fact"), ("fact", "fact")]);
+        recursive.add_funciton_call_edge(0, 1);
+        let json: Value = serde_json::from_str(&recursive.to_json()).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "nodes": [
+                    {"id": 0, "def_path": "main", "recursive": false},
+                    {"id": 1, "def_path": "fact", "recursive": true},
+                ],
+                "edges": [
+                    {"from": 0, "to": 1, "count": 2},
+                    {"from": 1, "to": 1, "count": 1},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        // main -> {fact, even, print}, fact -> fact, even <-> odd, odd -> print, and main -> fact twice
+        let mut metrics_graph = call_graph(
+            &["main", "fact", "even", "odd", "print"],
+            &[
+                ("main", "fact"),
+                ("main", "even"),
+                ("main", "print"),
+                ("fact", "fact"),
+                ("even", "odd"),
+                ("odd", "even"),
+                ("odd", "print"),
+            ],
+        );
+        metrics_graph.add_funciton_call_edge(0, 1);
+        let metrics = metrics_graph.metrics();
+        assert_eq!(metrics.function_count, 5);
+        // the edges are distinct caller-callee pairs
+        assert_eq!(metrics.edge_count, 7);
+        assert_eq!(metrics.recursive_count, 3);
+        assert_eq!(metrics.max_out_degree, 3);
+        assert_eq!(metrics.max_in_degree, 2);
+        let metrics = CallGraphInfo::new().metrics();
+        assert_eq!(
+            (
+                metrics.function_count,
+                metrics.edge_count,
+                metrics.recursive_count,
+                metrics.max_out_degree,
+                metrics.max_in_degree
+            ),
+            (0, 0, 0, 0, 0)
+        );
+    }
+}
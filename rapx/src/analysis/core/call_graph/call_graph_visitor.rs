@@ -1,6 +1,7 @@
 use super::call_graph_helper::CallGraphInfo;
+use crate::rap_warn;
 use regex::Regex;
-use rustc_hir::def_id::DefId;
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
 use rustc_middle::mir;
 use rustc_middle::ty::{FnDef, Instance, InstanceKind, TyCtxt};
 
@@ -9,6 +10,8 @@ pub struct CallGraphVisitor<'b, 'tcx> {
     def_id: DefId,
     body: &'tcx mir::Body<'tcx>,
     call_graph_info: &'b mut CallGraphInfo,
+    pub virtual_calls: bool, // add edges to all the implementors of the trait methods called virtually
+    pub skip_sysroot_calls: bool, // ignore the callees defined in the sysroot crates, e.g., core, alloc and std
 }
 
 impl<'b, 'tcx> CallGraphVisitor<'b, 'tcx> {
@@ -23,9 +26,21 @@ impl<'b, 'tcx> CallGraphVisitor<'b, 'tcx> {
             def_id: def_id,
             body: body,
             call_graph_info: call_graph_info,
+            virtual_calls: false,
+            skip_sysroot_calls: false,
         }
     }
 
+    // the crates shipped with the toolchain are loaded from the sysroot, whatever their names are
+    fn is_sysroot_fn(&self, def_id: DefId) -> bool {
+        def_id.krate != LOCAL_CRATE
+            && self
+                .tcx
+                .crate_extern_paths(def_id.krate)
+                .iter()
+                .any(|path| path.starts_with(&self.tcx.sess.sysroot))
+    }
+
     pub fn add_in_call_graph(
         &mut self,
         caller_def_path: &String,
@@ -50,13 +65,18 @@ impl<'b, 'tcx> CallGraphVisitor<'b, 'tcx> {
     pub fn visit(&mut self) {
         let caller_path_str = self.tcx.def_path_str(self.def_id);
         self.call_graph_info.add_node(self.def_id, &caller_path_str);
-        for (_, data) in self.body.basic_blocks.iter().enumerate() {
-            let terminator = data.terminator();
-            self.visit_terminator(&terminator);
+        for data in self.body.basic_blocks.iter() {
+            // the terminator can be missing in a malformed or unfinished body
+            if let Some(terminator) = &data.terminator {
+                self.visit_terminator(terminator);
+            }
         }
     }
 
     fn add_to_call_graph(&mut self, callee_def_id: DefId, is_virtual: Option<bool>) {
+        if self.skip_sysroot_calls && self.is_sysroot_fn(callee_def_id) {
+            return;
+        }
         let caller_def_path = self.tcx.def_path_str(self.def_id);
         let mut callee_def_path = self.tcx.def_path_str(callee_def_id);
         if let Some(judge) = is_virtual {
@@ -72,11 +92,36 @@ impl<'b, 'tcx> CallGraphVisitor<'b, 'tcx> {
         // let callee_location = self.tcx.def_span(callee_def_id);
         if callee_def_id == self.def_id {
             // Recursion
-            println!("Warning! Find a recursion function which may cause stackoverflow!")
+            rap_warn!(
+                "Find a recursion function which may cause stackoverflow: {}",
+                caller_def_path
+            );
         }
         self.add_in_call_graph(&caller_def_path, callee_def_id, &callee_def_path);
     }
 
+    // Conservatively treat every implementation of the trait method as a callee,
+    // including the default body of the method if some impls do not override it.
+    fn add_implementors_to_call_graph(&mut self, trait_item_def_id: DefId) {
+        let Some(trait_def_id) = self.tcx.trait_of_item(trait_item_def_id) else {
+            return;
+        };
+        let mut calls_default_body = false;
+        for impl_def_id in self.tcx.all_impls(trait_def_id) {
+            match self
+                .tcx
+                .impl_item_implementor_ids(impl_def_id)
+                .get(&trait_item_def_id)
+            {
+                Some(&impl_item_def_id) => self.add_to_call_graph(impl_item_def_id, None),
+                None => calls_default_body = true,
+            }
+        }
+        if calls_default_body && self.tcx.defaultness(trait_item_def_id).has_value() {
+            self.add_to_call_graph(trait_item_def_id, None);
+        }
+    }
+
     fn visit_terminator(&mut self, terminator: &mir::Terminator<'tcx>) {
         if let mir::TerminatorKind::Call { func, .. } = &terminator.kind {
             if let mir::Operand::Constant(constant) = func {
@@ -117,13 +162,136 @@ impl<'b, 'tcx> CallGraphVisitor<'b, 'tcx> {
                         };
                         if let Some(instance_def_id) = instance_def_id {
                             self.add_to_call_graph(instance_def_id, Some(is_virtual));
+                            if is_virtual && self.virtual_calls {
+                                self.add_implementors_to_call_graph(instance_def_id);
+                            }
                         }
                     } else {
                         // Although failing to get specific type, callee is still useful.
                         self.add_to_call_graph(*callee_def_id, None);
+                        if self.virtual_calls {
+                            self.add_implementors_to_call_graph(*callee_def_id);
+                        }
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{def_id_by_name, with_tcx};
+
+    // the def paths of the callees of the function in the call graph
+    fn callees(call_graph_info: &CallGraphInfo, caller: &str) -> Vec<String> {
+        let mut callees = call_graph_info
+            .get_callees_path(&String::from(caller))
+            .map(|callees| callees.into_iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        callees.sort();
+        callees
+    }
+
+    #[test]
+    fn test_visit_skips_blocks_without_terminators() {
+        let source = r#"
+            #[inline(never)]
+            pub fn first() {}
+            #[inline(never)]
+            pub fn second() {}
+            pub fn caller() {
+                first();
+                second();
+            }
+        "#;
+        with_tcx("missing_terminator", source, |tcx| {
+            let (def_id, second) = (def_id_by_name(tcx, "caller"), def_id_by_name(tcx, "second"));
+            let mut body = tcx.optimized_mir(def_id).clone();
+            // drop the terminator calling `second`
+            let basic_blocks = body.basic_blocks.as_mut();
+            let bb = basic_blocks
+                .indices()
+                .find(|bb| match &basic_blocks[*bb].terminator().kind {
+                    mir::TerminatorKind::Call { func, .. } => {
+                        matches!(func.const_fn_def(), Some((callee, _)) if callee == second)
+                    }
+                    _ => false,
+                })
+                .unwrap();
+            basic_blocks[bb].terminator = None;
+            let body = tcx.arena.alloc(body);
+            let mut call_graph_info = CallGraphInfo::new();
+            CallGraphVisitor::new(tcx, def_id, body, &mut call_graph_info).visit();
+            assert_eq!(callees(&call_graph_info, "caller"), vec!["first"]);
+        });
+    }
+
+    #[test]
+    fn test_virtual_calls_reach_all_impls() {
+        let source = r#"
+            pub trait Shape {
+                fn area(&self) -> u32;
+            }
+            pub struct Circle;
+            pub struct Square;
+            impl Shape for Circle {
+                fn area(&self) -> u32 {
+                    3
+                }
+            }
+            impl Shape for Square {
+                fn area(&self) -> u32 {
+                    4
+                }
+            }
+            pub fn total(shape: &dyn Shape) -> u32 {
+                shape.area()
+            }
+        "#;
+        with_tcx("virtual_calls", source, |tcx| {
+            let def_id = def_id_by_name(tcx, "total");
+            let mut call_graph_info = CallGraphInfo::new();
+            let mut visitor =
+                CallGraphVisitor::new(tcx, def_id, tcx.optimized_mir(def_id), &mut call_graph_info);
+            visitor.virtual_calls = true;
+            visitor.visit();
+            assert_eq!(
+                callees(&call_graph_info, "total"),
+                vec![
+                    "(dyn trait) <* as Shape>::area",
+                    "<Circle as Shape>::area",
+                    "<Square as Shape>::area",
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_skip_sysroot_calls() {
+        let source = r#"
+            #[inline(never)]
+            pub fn helper() {}
+            pub fn count(v: &Vec<u32>) -> usize {
+                helper();
+                v.len()
+            }
+        "#;
+        with_tcx("sysroot_calls", source, |tcx| {
+            let def_id = def_id_by_name(tcx, "count");
+            let body = tcx.optimized_mir(def_id);
+            let mut call_graph_info = CallGraphInfo::new();
+            CallGraphVisitor::new(tcx, def_id, body, &mut call_graph_info).visit();
+            assert_eq!(
+                callees(&call_graph_info, "count"),
+                vec!["helper", "std::vec::Vec::<T, A>::len"]
+            );
+            let mut call_graph_info = CallGraphInfo::new();
+            let mut visitor = CallGraphVisitor::new(tcx, def_id, body, &mut call_graph_info);
+            visitor.skip_sysroot_calls = true;
+            visitor.visit();
+            assert_eq!(callees(&call_graph_info, "count"), vec!["helper"]);
+        });
+    }
+}
@@ -1,22 +1,46 @@
 pub mod debug;
+pub mod export;
 pub mod graph;
+pub mod server;
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Write;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
 use std::process::Command;
 
+use rustc_data_structures::fingerprint::Fingerprint;
 use rustc_hir::def::DefKind;
 use rustc_hir::def_id::DefId;
-use rustc_middle::mir::Body;
-use rustc_middle::ty::TyCtxt;
+use rustc_hir::definitions::DefPathHash;
+use rustc_middle::mir::visit::{PlaceContext, Visitor};
+use rustc_middle::mir::{
+    traversal, Body, Local, Location, Operand, Place, PlaceElem, Terminator, TerminatorKind,
+};
+use rustc_middle::ty::{
+    EarlyBinder, GenericArgs, Instance, InstanceKind, ParamEnv, TyCtxt, TyKind,
+};
+use rustc_target::abi::FIRST_VARIANT;
 
-use graph::Graph;
+use crate::rap_error;
+use graph::{Graph, GraphBuildError, NodeOp, ProjectionKey};
+
+// The cost of building a graph, which helps to find out the pathologically large functions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildStats {
+    pub n_statements: usize,
+    pub n_terminators: usize,
+    pub n_nodes: usize,   //including the locals and the markers
+    pub n_markers: usize, //the nodes which are not locals in mir, e.g., projections and consts
+    pub n_edges: usize,
+}
 
 pub struct DataFlow<'tcx> {
     pub tcx: TyCtxt<'tcx>,
     pub graphs: HashMap<DefId, Graph>,
     pub debug: bool,
+    pub record_skipped_statms: bool, // record the non-assignment statements on the locals
+    pub skip_unreachable_blocks: bool, // ignore the blocks unreachable from the start block
+    pub record_switch_targets: bool, // add the values matched by the branches as edges
 }
 
 impl<'tcx> DataFlow<'tcx> {
@@ -25,6 +49,9 @@ impl<'tcx> DataFlow<'tcx> {
             tcx: tcx,
             graphs: HashMap::new(),
             debug,
+            record_skipped_statms: false,
+            skip_unreachable_blocks: false,
+            record_switch_targets: false,
         }
     }
 
@@ -35,6 +62,21 @@ impl<'tcx> DataFlow<'tcx> {
         }
     }
 
+    // Build the graphs and answer the queries from the stdin until it ends, see `server::serve`,
+    // where the functions are named by their def paths.
+    pub fn serve(&mut self) {
+        self.build_graphs();
+        let tcx = self.tcx;
+        let graphs = self
+            .graphs
+            .drain()
+            .map(|(def_id, graph)| (tcx.def_path_str(def_id), graph))
+            .collect();
+        if let Err(err) = server::serve(&graphs, io::stdin().lock(), io::stdout().lock()) {
+            rap_error!("The dataflow server stopped: {}", err);
+        }
+    }
+
     pub fn build_graphs(&mut self) {
         for local_def_id in self.tcx.iter_local_def_id() {
             if matches!(self.tcx.def_kind(local_def_id), DefKind::Fn) {
@@ -48,21 +90,352 @@ impl<'tcx> DataFlow<'tcx> {
         }
     }
 
-    fn build_graph(&self, def_id: DefId) -> Graph {
-        let body: &Body = self.tcx.optimized_mir(def_id);
-        let mut graph = Graph::new(def_id, body.span, body.arg_count, body.local_decls.len());
+    pub fn build_graph(&self, def_id: DefId) -> Graph {
+        let body = self.body_of(def_id);
+        self.build_graph_from_body(def_id, &body, None, None)
+    }
+
+    // The body to build the graph from, which is the optimized mir except for the coroutines, whose yields are
+    // lowered into a state machine by then. Their bodies before the transform are used instead if not stolen yet,
+    // e.g., when the crate is only checked as `cargo rapx` does.
+    fn body_of(&self, def_id: DefId) -> Cow<'tcx, Body<'tcx>> {
+        if let Some(local_def_id) = def_id.as_local() {
+            if self.tcx.is_coroutine(def_id) {
+                let (body, _) = self.tcx.mir_promoted(local_def_id);
+                if !body.is_stolen() {
+                    return Cow::Owned(body.borrow().clone());
+                }
+            }
+        }
+        Cow::Borrowed(self.tcx.optimized_mir(def_id))
+    }
+
+    // Same as `build_graph`, but fail if there is any construct the builder cannot model
+    // instead of returning a partial graph. All the errors are returned in the order they are met.
+    pub fn try_build_graph(&self, def_id: DefId) -> Result<Graph, Vec<GraphBuildError>> {
+        let mut graph = self.build_graph(def_id);
+        if graph.errors.is_empty() {
+            Ok(graph)
+        } else {
+            Err(std::mem::take(&mut graph.errors))
+        }
+    }
+
+    pub fn build_graph_with_stats(&self, def_id: DefId) -> (Graph, BuildStats) {
+        let body = self.body_of(def_id);
+        let mut stats = BuildStats::default();
+        let graph = self.build_graph_from_body(def_id, &body, None, Some(&mut stats));
+        (graph, stats)
+    }
+
+    // Build the graph of a monomorphized instance, whose callees are resolved with its generic args.
+    // The synthetic instances, e.g., drop glues and closure shims, are routed to `build_graph_shim`.
+    // None if the instance has no body, i.e., a virtual call, an intrinsic or a foreign function.
+    pub fn build_graph_for_instance(&self, instance: Instance<'tcx>) -> Option<Graph> {
+        match instance.def {
+            InstanceKind::Item(def_id) => {
+                if !self.tcx.is_mir_available(def_id) {
+                    return None;
+                }
+                let body = self.tcx.instance_mir(instance.def);
+                Some(self.build_graph_from_body(instance.def_id(), body, Some(instance), None))
+            }
+            _ => self.build_graph_shim(instance),
+        }
+    }
+
+    // Build the graph of a synthetic instance which has no `optimized_mir`, e.g., the drop glue of a type.
+    // Note that the graph is keyed by the def id of the shimmed item, e.g., `core::ptr::drop_in_place`
+    // for all the drop glues. None for the virtual calls and the intrinsics, which have no shims.
+    pub fn build_graph_shim(&self, instance: Instance<'tcx>) -> Option<Graph> {
+        match instance.def {
+            InstanceKind::Item(_) | InstanceKind::Virtual(..) | InstanceKind::Intrinsic(_) => None,
+            _ => {
+                let body = self.tcx.mir_shims(instance.def);
+                Some(self.build_graph_from_body(instance.def_id(), body, Some(instance), None))
+            }
+        }
+    }
+
+    fn build_graph_from_body(
+        &self,
+        def_id: DefId,
+        body: &Body<'tcx>,
+        instance: Option<Instance<'tcx>>,
+        mut stats: Option<&mut BuildStats>,
+    ) -> Graph {
         let basic_blocks = &body.basic_blocks;
-        for basic_block_data in basic_blocks.iter() {
-            for statement in basic_block_data.statements.iter() {
+        let mut graph = Graph::with_capacity(
+            def_id,
+            body.span,
+            body.arg_count,
+            body.local_decls.len(),
+            edge_capacity(body),
+        );
+        graph.set_field_names(self.resolve_field_names(body));
+        graph.set_ref_locals(
+            body.local_decls
+                .iter_enumerated()
+                .filter(|(_, local_decl)| local_decl.ty.is_ref())
+                .map(|(local, _)| local)
+                .collect(),
+        );
+        let reachable_blocks = if self.skip_unreachable_blocks {
+            Some(traversal::reachable_as_bitset(body))
+        } else {
+            None
+        };
+        for (bb, basic_block_data) in basic_blocks.iter_enumerated() {
+            if let Some(reachable_blocks) = &reachable_blocks {
+                if !reachable_blocks.contains(bb) {
+                    continue;
+                }
+            }
+            for (statement_index, statement) in basic_block_data.statements.iter().enumerate() {
+                graph.set_location(Location {
+                    block: bb,
+                    statement_index,
+                });
                 graph.add_statm_to_graph(&statement);
+                if self.record_skipped_statms {
+                    graph.add_skipped_statm_to_graph(statement);
+                }
             }
             if let Some(terminator) = &basic_block_data.terminator {
-                graph.add_terminator_to_graph(&terminator);
+                graph.set_location(body.terminator_loc(bb));
+                let switch_discr = graph.add_terminator_to_graph(&terminator);
+                if self.record_switch_targets {
+                    if let Some(discr) = switch_discr {
+                        graph.add_switch_targets_to_graph(terminator, discr);
+                    }
+                }
+                if let Some(instance) = instance {
+                    self.resolve_callee(&mut graph, instance, terminator);
+                }
+            }
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.n_statements += basic_block_data.statements.len();
+                stats.n_terminators += basic_block_data.terminator.is_some() as usize;
             }
         }
+        if let Some(stats) = stats {
+            stats.n_nodes = graph.nodes.len();
+            stats.n_markers = graph.nodes.len() - graph.n_locals;
+            stats.n_edges = graph.edges.len();
+        }
         graph
     }
 
+    // Replace the callee recorded for the call with the one resolved for the instance,
+    // e.g., `<T as Clone>::clone` is resolved to `<i32 as Clone>::clone` if T is i32.
+    fn resolve_callee(
+        &self,
+        graph: &mut Graph,
+        instance: Instance<'tcx>,
+        terminator: &Terminator<'tcx>,
+    ) {
+        if let TerminatorKind::Call {
+            func, destination, ..
+        } = &terminator.kind
+        {
+            if let Some(callee) = self.resolve_callee_instance(instance, func) {
+                // the call has just been added, so its op is the last one
+                let node = &mut graph.nodes[destination.local];
+                let seq = node.seq - 1;
+                if let NodeOp::Call(_) = node.ops[seq] {
+                    node.ops[seq] = NodeOp::Call(callee.def_id());
+                }
+            }
+        }
+    }
+
+    // the instance called by `func` in the body of the instance, with the generic args of the caller
+    fn resolve_callee_instance(
+        &self,
+        instance: Instance<'tcx>,
+        func: &Operand<'tcx>,
+    ) -> Option<Instance<'tcx>> {
+        let (callee_def_id, callee_args) = func.const_fn_def()?;
+        let param_env = ParamEnv::reveal_all();
+        let callee_args = instance.instantiate_mir_and_normalize_erasing_regions(
+            self.tcx,
+            param_env,
+            EarlyBinder::bind(callee_args),
+        );
+        Instance::try_resolve(self.tcx, param_env, callee_def_id, callee_args)
+            .ok()
+            .flatten()
+    }
+
+    // The instance of the call in the caller writing the destination, whose callee is resolved to the def id.
+    // The graph only keeps the def id, so the call is looked up in the mir again to get the generic args.
+    fn resolve_call_instance(
+        &self,
+        caller: Instance<'tcx>,
+        destination: Local,
+        callee: DefId,
+    ) -> Option<Instance<'tcx>> {
+        let body = self.tcx.instance_mir(caller.def);
+        body.basic_blocks
+            .iter()
+            // the terminator can be missing in a malformed or unfinished body
+            .filter_map(|basic_block_data| basic_block_data.terminator.as_ref())
+            .filter_map(|terminator| match &terminator.kind {
+                TerminatorKind::Call {
+                    func,
+                    destination: place,
+                    ..
+                } if place.local == destination => self.resolve_callee_instance(caller, func),
+                _ => None,
+            })
+            .find(|instance| instance.def_id() == callee)
+    }
+
+    // Find the (source, sink function, sink) triples such that the value of a source local in the entry flows into
+    // a sink local, following the calls into the local callees: the arguments flow into the parameters of the callee,
+    // and the return value of the callee flows back into the destination of the call. The calls nested deeper than
+    // `MAX_CALL_DEPTH`, and the callees without mir, are treated as opaque, i.e., the arguments flow into the destination.
+    // The callees are resolved with the generic args of their callers, e.g., a trait method called in a generic
+    // function is followed into the impl, and the graphs are built for the instances once.
+    // The predicates are given the graph of the function, so that the ops and the def id can be checked.
+    // The destinations of the calls followed into are checked by `is_sink` as well.
+    pub fn flows_across_calls<S, K>(
+        &self,
+        entry: DefId,
+        is_source: S,
+        is_sink: K,
+    ) -> Vec<(Local, DefId, Local)>
+    where
+        S: Fn(&Graph, Local) -> bool,
+        K: Fn(&Graph, Local) -> bool,
+    {
+        const MAX_CALL_DEPTH: usize = 8;
+        // (function, local, the call sites to return to as (caller, destination))
+        type State<'tcx> = (Instance<'tcx>, Local, Vec<(Instance<'tcx>, Local)>);
+        let entry = Instance::new(entry, GenericArgs::identity_for_item(self.tcx, entry));
+        let mut built = HashMap::new();
+        let mut flows = vec![];
+        let sources: Vec<Local> = {
+            let Some(graph) = self.instance_graph_or_build(&mut built, entry) else {
+                return flows;
+            };
+            graph
+                .nodes
+                .indices()
+                .filter(|local| is_source(graph, *local))
+                .collect()
+        };
+        for source in sources {
+            let mut visited: HashSet<State> = HashSet::new();
+            let mut worklist: VecDeque<State> = VecDeque::from([(entry, source, vec![])]);
+            while let Some(state) = worklist.pop_front() {
+                if !visited.insert(state.clone()) {
+                    continue;
+                }
+                let (instance, local, call_stack) = state;
+                let def_id = instance.def_id();
+                let Some(graph) = self.instance_graph_or_build(&mut built, instance) else {
+                    continue;
+                };
+                if local.as_usize() >= graph.nodes.len() {
+                    continue; //e.g., more arguments than parameters
+                }
+                if (instance, local) != (entry, source) && is_sink(graph, local) {
+                    let flow = (source, def_id, local);
+                    if !flows.contains(&flow) {
+                        flows.push(flow);
+                    }
+                }
+                for edge_idx in graph.nodes[local].out_edges.iter() {
+                    let edge = &graph.edges[*edge_idx];
+                    let callee = match graph.nodes[edge.dst].ops[edge.seq] {
+                        NodeOp::Call(callee)
+                            if callee.is_local()
+                                && self.tcx.is_mir_available(callee)
+                                && call_stack.len() < MAX_CALL_DEPTH =>
+                        {
+                            self.resolve_call_instance(instance, edge.dst, callee)
+                        }
+                        _ => None,
+                    };
+                    let Some(callee) = callee else {
+                        worklist.push_back((instance, edge.dst, call_stack.clone()));
+                        continue;
+                    };
+                    // the destination is not visited in the caller then, but it can still be a sink, e.g., `sink(_1)`
+                    if is_sink(graph, edge.dst) && !flows.contains(&(source, def_id, edge.dst)) {
+                        flows.push((source, def_id, edge.dst));
+                    }
+                    // the position of the argument among the operands of the call
+                    let arg_idx = graph.nodes[edge.dst]
+                        .in_edges
+                        .iter()
+                        .filter(|idx| graph.edges[**idx].seq == edge.seq)
+                        .position(|idx| idx == edge_idx)
+                        .unwrap();
+                    let mut callee_stack = call_stack.clone();
+                    callee_stack.push((instance, edge.dst));
+                    worklist.push_back((callee, Local::from_usize(arg_idx + 1), callee_stack));
+                }
+                if local == graph.return_local() {
+                    let mut caller_stack = call_stack;
+                    if let Some((caller, dst)) = caller_stack.pop() {
+                        worklist.push_back((caller, dst, caller_stack));
+                    }
+                }
+            }
+        }
+        flows
+    }
+
+    fn instance_graph_or_build<'a>(
+        &'a self,
+        built: &'a mut HashMap<Instance<'tcx>, Option<Graph>>,
+        instance: Instance<'tcx>,
+    ) -> Option<&'a Graph> {
+        built
+            .entry(instance)
+            .or_insert_with(|| self.build_graph_for_instance(instance))
+            .as_ref()
+    }
+
+    // Resolve the names of the struct fields in the places of the body, see `Graph::set_field_names`.
+    fn resolve_field_names(&self, body: &Body<'tcx>) -> HashMap<ProjectionKey, String> {
+        struct FieldNameVisitor<'a, 'tcx> {
+            tcx: TyCtxt<'tcx>,
+            body: &'a Body<'tcx>,
+            field_names: HashMap<ProjectionKey, String>,
+        }
+
+        impl<'tcx> Visitor<'tcx> for FieldNameVisitor<'_, 'tcx> {
+            fn visit_place(&mut self, place: &Place<'tcx>, _: PlaceContext, _: Location) {
+                for (i, place_elem) in place.projection.iter().enumerate() {
+                    let PlaceElem::Field(field_idx, _) = place_elem else {
+                        continue;
+                    };
+                    let base_ty =
+                        Place::ty_from(place.local, &place.projection[..i], self.body, self.tcx);
+                    let TyKind::Adt(adt_def, _) = base_ty.ty.kind() else {
+                        continue;
+                    };
+                    let variant = adt_def.variant(base_ty.variant_index.unwrap_or(FIRST_VARIANT));
+                    self.field_names.insert(
+                        Graph::projection_key(place.local, &place.projection[..=i]),
+                        variant.fields[field_idx].name.to_string(),
+                    );
+                }
+            }
+        }
+
+        let mut visitor = FieldNameVisitor {
+            tcx: self.tcx,
+            body,
+            field_names: HashMap::new(),
+        };
+        visitor.visit_body(body);
+        visitor.field_names
+    }
+
     pub fn draw_graphs(&self) {
         let dir_name = "DataflowGraph";
 
@@ -80,9 +453,8 @@ impl<'tcx> DataFlow<'tcx> {
             let name = self.tcx.def_path_str(def_id);
             let dot_file_name = format!("DataflowGraph/{}.dot", &name);
             let png_file_name = format!("DataflowGraph/{}.png", &name);
-            let mut file = File::create(&dot_file_name).expect("Unable to create file.");
-            let dot = graph.to_dot_graph(&self.tcx);
-            file.write_all(dot.as_bytes())
+            graph
+                .write_dot_to_path(&self.tcx, &dot_file_name)
                 .expect("Unable to write data.");
 
             Command::new("dot")
@@ -92,3 +464,1176 @@ impl<'tcx> DataFlow<'tcx> {
         }
     }
 }
+
+// The edges reserved for building the graph of a body, where most statements and terminators add about two edges.
+fn edge_capacity(body: &Body<'_>) -> usize {
+    body.basic_blocks
+        .iter()
+        .map(|basic_block_data| basic_block_data.statements.len() + 1)
+        .sum::<usize>()
+        * 2
+}
+
+// Build the graphs of all the functions and methods with mir in the local crate by the default builder,
+// which is the entry point when no option of `DataFlow` is needed.
+// Unlike `DataFlow::build_graphs`, the associated functions, e.g., the methods in impls, are included.
+pub fn build_graphs_for_crate(tcx: TyCtxt<'_>) -> HashMap<DefId, Graph> {
+    let dataflow = DataFlow::new(tcx, false);
+    tcx.mir_keys(())
+        .iter()
+        .map(|local_def_id| local_def_id.to_def_id())
+        .filter(|def_id| {
+            matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn)
+                && tcx.is_mir_available(def_id)
+        })
+        .map(|def_id| (def_id, dataflow.build_graph(def_id)))
+        .collect()
+}
+
+// Reuse the graphs of the functions built before, e.g., for the repeated queries from an editor.
+// The graphs are keyed by the def path hashes, which are stable across compilation sessions, and each of them is
+// rebuilt once the fingerprint of its function changes, see `GraphCache::fingerprint`.
+#[derive(Default)]
+pub struct GraphCache {
+    entries: HashMap<DefPathHash, (Option<Fingerprint>, Graph)>,
+    pub n_builds: usize, //how many graphs have been built, i.e., the cache misses
+}
+
+impl GraphCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The hash of the source of a local function including its body, or the hash of the crate of an external one.
+    // None if the compiler keeps no such hash, e.g., for binaries built without incremental compilation,
+    // in which case the graph is never reused.
+    pub fn fingerprint(tcx: TyCtxt<'_>, def_id: DefId) -> Option<Fingerprint> {
+        match def_id.as_local() {
+            Some(local_def_id) => {
+                let owner = tcx.local_def_id_to_hir_id(local_def_id).owner;
+                tcx.hir_owner_nodes(owner).opt_hash_including_bodies
+            }
+            None => {
+                let crate_hash = tcx.crate_hash(def_id.krate).as_u128();
+                Some(Fingerprint::new(
+                    crate_hash as u64,
+                    (crate_hash >> 64) as u64,
+                ))
+            }
+        }
+    }
+
+    pub fn get_or_build(&mut self, tcx: TyCtxt<'_>, def_id: DefId) -> &Graph {
+        let key = tcx.def_path_hash(def_id);
+        let fingerprint = Self::fingerprint(tcx, def_id);
+        let is_fresh = matches!(
+            self.entries.get(&key),
+            Some((cached, _)) if fingerprint.is_some() && *cached == fingerprint
+        );
+        if !is_fresh {
+            self.n_builds += 1;
+            let graph = DataFlow::new(tcx, false).build_graph(def_id);
+            self.entries.insert(key, (fingerprint, graph));
+        }
+        &self.entries[&key].1
+    }
+
+    pub fn get(&self, tcx: TyCtxt<'_>, def_id: DefId) -> Option<&Graph> {
+        self.entries
+            .get(&tcx.def_path_hash(def_id))
+            .map(|(_, graph)| graph)
+    }
+
+    pub fn invalidate(&mut self, tcx: TyCtxt<'_>, def_id: DefId) {
+        self.entries.remove(&tcx.def_path_hash(def_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::log::span_to_string;
+    use crate::utils::test_utils::{def_id_by_name, with_tcx};
+    use graph::{AggKind, EdgeOp, SkippedStatm};
+    use rustc_middle::mir::{BasicBlockData, BinOp, Rvalue, SourceInfo, Statement, StatementKind};
+    use rustc_span::DUMMY_SP;
+
+    #[test]
+    fn test_build_graph_for_drop_glue() {
+        let source = r#"
+            pub struct Wrapper(pub Vec<i32>);
+            pub trait Shape {
+                fn area(&self) -> u32;
+            }
+        "#;
+        with_tcx("drop_glue", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let ty = tcx
+                .type_of(def_id_by_name(tcx, "Wrapper"))
+                .instantiate_identity();
+            let drop_glue = Instance::resolve_drop_in_place(tcx, ty);
+            assert!(matches!(drop_glue.def, InstanceKind::DropGlue(_, Some(_))));
+            let graph = dataflow.build_graph_for_instance(drop_glue).unwrap();
+            assert_eq!(graph.argc, 1);
+            assert!(!graph.edges.is_empty());
+            let area = def_id_by_name(tcx, "area");
+            let virtual_call = Instance {
+                def: InstanceKind::Virtual(area, 0),
+                args: GenericArgs::identity_for_item(tcx, area),
+            };
+            assert!(dataflow.build_graph_for_instance(virtual_call).is_none());
+        });
+    }
+
+    // the values matched by the branches, which all start from the marker of the discriminant
+    fn switch_values(graph: &Graph) -> Vec<(u128, Local)> {
+        let mut values: Vec<(u128, Local)> = graph
+            .edges
+            .iter()
+            .filter_map(|edge| match edge.op {
+                EdgeOp::SwitchValue(value) => {
+                    assert!(matches!(graph.nodes[edge.src].ops[0], NodeOp::SwitchDiscr));
+                    Some((value, graph.edges[graph.nodes[edge.src].in_edges[0]].src))
+                }
+                _ => None,
+            })
+            .collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn test_small_body_does_not_reallocate_edges() {
+        let source = r#"
+            pub fn area(w: u32, h: u32) -> u32 {
+                let s = w * h;
+                if s > 100 { 100 } else { s }
+            }
+        "#;
+        with_tcx("edge_capacity", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let def_id = def_id_by_name(tcx, "area");
+            let edge_cap = edge_capacity(&dataflow.body_of(def_id));
+            let graph = dataflow.build_graph(def_id);
+            assert!(graph.edges.len() <= edge_cap);
+            // the reserved storage is still in use, i.e., it has never grown
+            let reserved = Graph::with_capacity(def_id, DUMMY_SP, 2, 0, edge_cap);
+            assert_eq!(graph.edges.raw.capacity(), reserved.edges.raw.capacity());
+        });
+    }
+
+    #[test]
+    fn test_global_seq_increases_across_blocks() {
+        let source = r#"
+            fn double(x: u32) -> u32 {
+                x * 2
+            }
+            pub fn twice(x: u32) -> u32 {
+                let a = x + 1;
+                let b = double(a);
+                b + a
+            }
+        "#;
+        with_tcx("global_seq", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let def_id = def_id_by_name(tcx, "twice");
+            let body = dataflow.body_of(def_id);
+            let graph = dataflow.build_graph(def_id);
+            // the global seqs of the writes to the locals, in the order of the blocks and their statements
+            let mut n_writes: HashMap<Local, usize> = HashMap::new();
+            let mut global_seqs = vec![];
+            let mut record = |local: Local| {
+                let n_write = n_writes.entry(local).or_default();
+                global_seqs.push(graph.nodes[local].global_seq[*n_write]);
+                *n_write += 1;
+            };
+            for basic_block_data in body.basic_blocks.iter() {
+                for statement in basic_block_data.statements.iter() {
+                    if let StatementKind::Assign(box (place, _)) = &statement.kind {
+                        if let Some(local) = place.as_local() {
+                            record(local);
+                        }
+                    }
+                }
+                if let TerminatorKind::Call { destination, .. } =
+                    &basic_block_data.terminator().kind
+                {
+                    record(destination.local);
+                }
+            }
+            // the call to `double` ends the first block
+            assert!(body.basic_blocks.len() >= 2);
+            assert!(global_seqs.len() >= 3);
+            assert!(global_seqs.windows(2).all(|pair| pair[0] < pair[1]));
+        });
+    }
+
+    #[test]
+    fn test_switch_targets_of_integer_match() {
+        let source = r#"
+            pub fn classify(x: u32) -> u32 {
+                match x {
+                    1 => 10,
+                    2 => 20,
+                    _ => 0,
+                }
+            }
+        "#;
+        with_tcx("switch_integer", source, |tcx| {
+            let mut dataflow = DataFlow::new(tcx, false);
+            dataflow.record_switch_targets = true;
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "classify"));
+            let x = Local::from_usize(1);
+            assert_eq!(switch_values(&graph), vec![(1, x), (2, x)]);
+        });
+    }
+
+    #[test]
+    fn test_switch_targets_of_field_discriminant() {
+        let source = r#"
+            pub fn pick(pair: &(u8, bool)) -> u8 {
+                match pair.0 {
+                    3 => 1,
+                    _ => 0,
+                }
+            }
+        "#;
+        with_tcx("switch_field", source, |tcx| {
+            let mut dataflow = DataFlow::new(tcx, false);
+            dataflow.record_switch_targets = true;
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "pick"));
+            let values = switch_values(&graph);
+            // `switchInt(copy ((*_1).0))`, whose discriminant is the marker of the field
+            assert_eq!(values.len(), 1);
+            let (value, field) = values[0];
+            assert_eq!(value, 3);
+            assert!(field.as_usize() >= graph.n_locals);
+            assert!(matches!(
+                graph.edges[graph.nodes[field].in_edges[0]].op,
+                EdgeOp::Field(_)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_try_build_graph_returns_all_errors() {
+        let source = r#"
+            #![feature(core_intrinsics)]
+            #![allow(internal_features)]
+            pub fn slices(p: *const u8, n: usize) -> (*const [u8], *const [u8]) {
+                (
+                    std::intrinsics::aggregate_raw_ptr(p, n),
+                    std::intrinsics::aggregate_raw_ptr(p, 0),
+                )
+            }
+            pub fn id(x: u32) -> u32 {
+                x
+            }
+        "#;
+        with_tcx("try_build", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            assert!(dataflow.try_build_graph(def_id_by_name(tcx, "id")).is_ok());
+            // the raw pointers built from the parts are the aggregates not modeled yet
+            let Err(errors) = dataflow.try_build_graph(def_id_by_name(tcx, "slices")) else {
+                panic!("the graph should fail to build");
+            };
+            assert_eq!(errors.len(), 2);
+            assert!(errors
+                .iter()
+                .all(|error| matches!(error, GraphBuildError::UnsupportedAggregate(_))));
+        });
+    }
+
+    #[test]
+    fn test_unsupported_aggregate_is_err_node() {
+        let source = r#"
+            #![feature(core_intrinsics)]
+            #![allow(internal_features)]
+            pub fn slices(p: *const u8, n: usize) -> (*const [u8], *const [u8]) {
+                (
+                    std::intrinsics::aggregate_raw_ptr(p, n),
+                    std::intrinsics::aggregate_raw_ptr(p, 0),
+                )
+            }
+        "#;
+        with_tcx("err_node", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "slices"));
+            let err_locals: Vec<Local> = graph
+                .nodes
+                .iter_enumerated()
+                .filter(|(_, node)| node.ops.iter().any(|op| matches!(op, NodeOp::Err)))
+                .map(|(local, _)| local)
+                .collect();
+            // one for each raw pointer, which are then put into the returned tuple as usual
+            assert_eq!(err_locals.len(), 2);
+            assert!(err_locals
+                .iter()
+                .all(|local| local.as_usize() < graph.n_locals));
+            assert_eq!(graph.errors.len(), 2);
+            let ret = &graph.nodes[Local::from_usize(0)];
+            assert!(matches!(ret.ops[0], NodeOp::Aggregate(AggKind::Tuple)));
+            let ret_srcs: Vec<Local> = ret
+                .in_edges
+                .iter()
+                .map(|edge_idx| graph.edges[*edge_idx].src)
+                .collect();
+            assert_eq!(ret_srcs, err_locals);
+        });
+    }
+
+    #[test]
+    fn test_graph_cache_builds_once() {
+        let source = r#"
+            pub fn one() -> u32 {
+                1
+            }
+            pub fn two() -> u32 {
+                2
+            }
+        "#;
+        with_tcx("graph_cache", source, |tcx| {
+            let (one, two) = (def_id_by_name(tcx, "one"), def_id_by_name(tcx, "two"));
+            let mut cache = GraphCache::new();
+            cache.get_or_build(tcx, one);
+            cache.get_or_build(tcx, one);
+            assert_eq!(cache.n_builds, 1);
+            cache.get_or_build(tcx, two);
+            assert_eq!(cache.n_builds, 2);
+            cache.invalidate(tcx, one);
+            assert!(cache.get(tcx, one).is_none());
+            cache.get_or_build(tcx, one);
+            assert_eq!(cache.n_builds, 3);
+        });
+    }
+
+    #[test]
+    fn test_graph_cache_rebuilds_changed_functions() {
+        let source = r#"
+            pub fn one() -> u32 {
+                1
+            }
+            pub fn two() -> u32 {
+                2
+            }
+        "#;
+        let mut cache = GraphCache::new();
+        with_tcx("graph_cache_change", source, |tcx| {
+            cache.get_or_build(tcx, def_id_by_name(tcx, "one"));
+            cache.get_or_build(tcx, def_id_by_name(tcx, "two"));
+        });
+        assert_eq!(cache.n_builds, 2);
+        // the next compilation session sees an edited `two`
+        let edited = source.replace("\n                2\n", "\n                3\n");
+        with_tcx("graph_cache_change", &edited, |tcx| {
+            let one = def_id_by_name(tcx, "one");
+            assert!(GraphCache::fingerprint(tcx, one).is_some());
+            cache.get_or_build(tcx, one);
+            assert_eq!(cache.n_builds, 2);
+            cache.get_or_build(tcx, def_id_by_name(tcx, "two"));
+            assert_eq!(cache.n_builds, 3);
+        });
+    }
+
+    #[test]
+    fn test_yield_of_async_fn() {
+        let source = r#"
+            pub async fn ready() -> u32 {
+                1
+            }
+            pub async fn wait() -> u32 {
+                ready().await + 1
+            }
+        "#;
+        with_tcx("async_yield", source, |tcx| {
+            let wait = def_id_by_name(tcx, "wait");
+            let coroutine = tcx
+                .iter_local_def_id()
+                .map(|local_def_id| local_def_id.to_def_id())
+                .find(|def_id| tcx.is_coroutine(*def_id) && tcx.parent(*def_id) == wait)
+                .unwrap();
+            let graph = DataFlow::new(tcx, false).build_graph(coroutine);
+            // the yielded value flows into a marker, and the resume argument is written by the resumer
+            let markers: Vec<&graph::GraphNode> = graph.nodes.raw[graph.n_locals..]
+                .iter()
+                .filter(|node| matches!(node.ops[0], NodeOp::Yield))
+                .collect();
+            assert!(!markers.is_empty());
+            assert!(markers.iter().all(|node| node.in_edges.len() == 1));
+            assert!(graph.nodes.raw[..graph.n_locals]
+                .iter()
+                .any(|node| node.ops.iter().any(|op| matches!(op, NodeOp::Yield))));
+        });
+    }
+
+    #[test]
+    fn test_field_names_by_projection() {
+        let source = r#"
+            pub struct Point {
+                pub x: u32,
+                pub y: u32,
+            }
+            pub enum Shape {
+                Circle { radius: u32 },
+                Square { side: u32 },
+            }
+            pub fn sum(p: &Point, s: Shape) -> u32 {
+                let size = match s {
+                    Shape::Circle { radius } => radius,
+                    Shape::Square { side } => side,
+                };
+                p.x + p.y + size
+            }
+        "#;
+        with_tcx("field_names", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "sum"));
+            let fields: HashSet<&str> = graph
+                .edges
+                .iter()
+                .filter_map(|edge| match &edge.op {
+                    EdgeOp::Field(field) => Some(field.as_str()),
+                    _ => None,
+                })
+                .collect();
+            // the fields of the tuples, e.g., the results of the checked additions, have no names
+            assert!(fields.is_superset(&HashSet::from(["x", "y", "radius", "side", "0"])));
+        });
+    }
+
+    #[test]
+    fn test_resolve_callee_of_generic_call() {
+        let source = r#"
+            pub fn dup<T: Clone>(x: &T) -> T {
+                x.clone()
+            }
+        "#;
+        with_tcx("resolve_callee", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let dup = def_id_by_name(tcx, "dup");
+            let args = tcx.mk_args(&[tcx.types.i32.into()]);
+            let instance = Instance::try_resolve(tcx, ParamEnv::reveal_all(), dup, args)
+                .unwrap()
+                .unwrap();
+            let body = tcx.instance_mir(instance.def);
+            let (terminator, destination) = body
+                .basic_blocks
+                .iter()
+                .filter_map(|basic_block_data| basic_block_data.terminator.as_ref())
+                .find_map(|terminator| match &terminator.kind {
+                    TerminatorKind::Call { destination, .. } => {
+                        Some((terminator, destination.local))
+                    }
+                    _ => None,
+                })
+                .unwrap();
+            let callee = |graph: &Graph| match graph.nodes[destination].ops[..] {
+                [NodeOp::Call(callee)] => callee,
+                _ => panic!("the destination should be written by the call only"),
+            };
+            let mut graph = Graph::new(dup, body.span, body.arg_count, body.local_decls.len());
+            graph.add_terminator_to_graph(terminator);
+            // `<T as Clone>::clone` before the resolution
+            assert!(tcx.trait_of_item(callee(&graph)).is_some());
+            dataflow.resolve_callee(&mut graph, instance, terminator);
+            // `<i32 as Clone>::clone` after it
+            let impl_def_id = tcx.impl_of_method(callee(&graph)).unwrap();
+            assert_eq!(
+                tcx.type_of(impl_def_id).instantiate_identity(),
+                tcx.types.i32
+            );
+        });
+    }
+
+    #[test]
+    fn test_flows_across_calls_into_trait_impl() {
+        let source = r#"
+            pub trait Sink {
+                fn put(x: u32) -> u32;
+            }
+            pub struct Log;
+            impl Sink for Log {
+                fn put(x: u32) -> u32 {
+                    x
+                }
+            }
+            pub fn forward<S: Sink>(x: u32) -> u32 {
+                S::put(x)
+            }
+            pub fn entry(a: u32) -> u32 {
+                forward::<Log>(a)
+            }
+        "#;
+        with_tcx("flows_across_calls", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let entry = def_id_by_name(tcx, "entry");
+            // `S::put` in `forward` is only resolved to the impl with the generic args from `entry`
+            let put = tcx
+                .iter_local_def_id()
+                .map(|local_def_id| local_def_id.to_def_id())
+                .find(|def_id| {
+                    tcx.opt_item_name(*def_id)
+                        .is_some_and(|name| name.as_str() == "put")
+                        && tcx.impl_of_method(*def_id).is_some()
+                })
+                .unwrap();
+            let param = Local::from_usize(1);
+            let flows = dataflow.flows_across_calls(
+                entry,
+                |graph, local| graph.def_id == entry && local == param,
+                |graph, local| graph.def_id == put && local == param,
+            );
+            assert_eq!(flows, vec![(param, put, param)]);
+        });
+    }
+
+    #[test]
+    fn test_const_node_keeps_operand_span() {
+        let source = r#"
+            pub fn scale(x: u32) -> u32 {
+                x * 7
+            }
+        "#;
+        with_tcx("const_span", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "scale"));
+            let spans: Vec<String> = graph
+                .nodes
+                .iter()
+                .filter(|node| matches!(node.ops[0], NodeOp::Const(_)))
+                .map(|node| tcx.sess.source_map().span_to_snippet(node.span).unwrap())
+                .collect();
+            assert!(spans.contains(&String::from("7")));
+        });
+    }
+
+    #[test]
+    fn test_record_skipped_statms() {
+        let source = r#"
+            pub fn id(x: u32) -> u32 {
+                let y = x;
+                y
+            }
+        "#;
+        with_tcx("skipped_statms", source, |tcx| {
+            let def_id = def_id_by_name(tcx, "id");
+            let mut dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id);
+            assert!(graph
+                .nodes
+                .iter()
+                .all(|node| node.skipped_statms.is_empty()));
+            dataflow.record_skipped_statms = true;
+            let graph = dataflow.build_graph(def_id);
+            // `y` is `_2`
+            assert!(matches!(
+                graph.nodes[Local::from_usize(2)].skipped_statms[..],
+                [SkippedStatm::StorageLive, SkippedStatm::StorageDead]
+            ));
+            // the statements are only recorded, without any edge
+            assert_eq!(graph.edges.len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_drop_flows_into_marker() {
+        let source = r#"
+            pub fn consume(v: Vec<u8>) {
+                let _w = v;
+            }
+        "#;
+        with_tcx("drop_marker", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "consume"));
+            let drops: Vec<Local> = graph
+                .nodes
+                .iter_enumerated()
+                .filter(|(_, node)| matches!(node.ops[0], NodeOp::Drop))
+                .map(|(local, _)| local)
+                .collect();
+            // the moved value `_w` is dropped at the end of its scope
+            assert_eq!(drops.len(), 1);
+            assert!(drops[0].as_usize() >= graph.n_locals);
+            let dropped: Vec<Local> = graph.incoming(drops[0]).map(|edge| edge.src).collect();
+            assert_eq!(dropped, vec![Local::from_usize(2)]);
+        });
+    }
+
+    #[test]
+    fn test_switch_discr_marker_of_branch() {
+        let source = r#"
+            pub fn choose(flag: bool) -> u32 {
+                if flag { 1 } else { 2 }
+            }
+        "#;
+        with_tcx("switch_discr", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "choose"));
+            let discrs: Vec<Local> = graph
+                .nodes
+                .iter_enumerated()
+                .filter(|(_, node)| matches!(node.ops[0], NodeOp::SwitchDiscr))
+                .map(|(local, _)| local)
+                .collect();
+            assert_eq!(discrs.len(), 1);
+            // `_2 = copy _1; switchInt(move _2)`, where the flag decides the branch without a value flowing out
+            let srcs: Vec<Local> = graph.incoming(discrs[0]).map(|edge| edge.src).collect();
+            assert_eq!(srcs, vec![Local::from_usize(2)]);
+            assert!(graph.is_connected(Local::from_usize(1), discrs[0]));
+            assert_eq!(graph.outgoing(discrs[0]).count(), 0);
+        });
+    }
+
+    #[test]
+    fn test_direct_callees_are_distinct() {
+        let source = r#"
+            fn one() -> u32 {
+                1
+            }
+            fn two() -> u32 {
+                2
+            }
+            pub fn sum() -> u32 {
+                one().wrapping_add(two()).wrapping_add(one())
+            }
+        "#;
+        with_tcx("direct_callees", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "sum"));
+            let callees: Vec<String> = graph
+                .direct_callees()
+                .into_iter()
+                .map(|def_id| tcx.def_path_str(def_id))
+                .collect();
+            // the last call writes `_0`, and `one` is listed once
+            assert_eq!(
+                callees,
+                vec!["core::num::<impl u32>::wrapping_add", "one", "two"]
+            );
+        });
+    }
+
+    #[test]
+    fn test_group_by_span_of_checked_add() {
+        let source = r#"
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#;
+        with_tcx("group_by_span", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "add"));
+            let groups = graph.group_by_span();
+            for (span, locals) in groups.iter() {
+                assert!(locals.iter().all(|local| local.as_usize() < graph.n_locals));
+                assert!(locals
+                    .iter()
+                    .all(|local| span_to_string(graph.nodes[*local].span) == *span));
+            }
+            // the checked result and the sum projected from it are both on `a + b`
+            let snippet = |local: &Local| {
+                tcx.sess
+                    .source_map()
+                    .span_to_snippet(graph.nodes[*local].span)
+                    .unwrap()
+            };
+            assert!(groups
+                .values()
+                .any(|locals| locals.len() >= 2
+                    && locals.iter().all(|local| snippet(local) == "a + b")));
+        });
+    }
+
+    #[test]
+    fn test_skip_unreachable_blocks() {
+        let source = r#"
+            pub fn five(_x: u32) -> u32 {
+                5
+            }
+        "#;
+        with_tcx("unreachable_blocks", source, |tcx| {
+            let def_id = def_id_by_name(tcx, "five");
+            let mut dataflow = DataFlow::new(tcx, false);
+            // a block jumped to by nothing: `_0 = copy _1; return;`
+            let mut body = dataflow.body_of(def_id).into_owned();
+            let (ret, x) = (Local::from_usize(0), Local::from_usize(1));
+            let source_info = SourceInfo::outermost(DUMMY_SP);
+            body.basic_blocks_mut().push(BasicBlockData {
+                statements: vec![Statement {
+                    source_info,
+                    kind: StatementKind::Assign(Box::new((
+                        Place::from(ret),
+                        Rvalue::Use(Operand::Copy(Place::from(x))),
+                    ))),
+                }],
+                terminator: Some(Terminator {
+                    source_info,
+                    kind: TerminatorKind::Return,
+                }),
+                is_cleanup: false,
+            });
+            let graph = dataflow.build_graph_from_body(def_id, &body, None, None);
+            assert!(graph.is_connected(x, ret));
+            dataflow.skip_unreachable_blocks = true;
+            let graph = dataflow.build_graph_from_body(def_id, &body, None, None);
+            assert!(!graph.is_connected(x, ret));
+        });
+    }
+
+    #[test]
+    fn test_build_graph_with_stats() {
+        let source = r#"
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#;
+        with_tcx("build_stats", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let def_id = def_id_by_name(tcx, "add");
+            let body = dataflow.body_of(def_id);
+            let (graph, stats) = dataflow.build_graph_with_stats(def_id);
+            let n_statements: usize = body
+                .basic_blocks
+                .iter()
+                .map(|basic_block_data| basic_block_data.statements.len())
+                .sum();
+            assert_eq!(stats.n_statements, n_statements);
+            // the checked add ends the first block with an assert
+            assert_eq!(stats.n_terminators, body.basic_blocks.len());
+            assert!(stats.n_terminators >= 2);
+            assert_eq!(stats.n_nodes, graph.nodes.len());
+            assert_eq!(stats.n_markers, graph.nodes.len() - body.local_decls.len());
+            assert!(stats.n_markers > 0);
+            assert_eq!(stats.n_edges, graph.edges.len());
+        });
+    }
+
+    #[test]
+    fn test_allocation_sites_of_rustc_box() {
+        let source = r#"
+            #![feature(rustc_attrs)]
+            #![allow(internal_features)]
+            pub fn boxed(x: u32) -> Box<u32> {
+                #[rustc_box]
+                Box::new(x)
+            }
+            pub fn unboxed(x: u32) -> u32 {
+                x
+            }
+        "#;
+        with_tcx("allocation_sites", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "boxed"));
+            let sites = graph.allocation_sites();
+            assert_eq!(sites.len(), 1);
+            let (site, span) = sites[0];
+            assert!(site.as_usize() < graph.n_locals);
+            let snippet = tcx.sess.source_map().span_to_snippet(span).unwrap();
+            assert!(snippet.ends_with("Box::new(x)"));
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "unboxed"));
+            assert!(graph.allocation_sites().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_inline_asm_outputs_depend_on_inputs() {
+        let source = r#"
+            pub fn lea(a: u64, b: u64) -> u64 {
+                let out: u64;
+                unsafe {
+                    std::arch::asm!("lea {0}, [{1} + {2}]", out(reg) out, in(reg) a, in(reg) b);
+                }
+                out
+            }
+        "#;
+        with_tcx("inline_asm", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "lea"));
+            let outs: Vec<Local> = graph
+                .nodes
+                .iter_enumerated()
+                .filter(|(_, node)| node.ops.iter().any(|op| matches!(op, NodeOp::InlineAsm)))
+                .map(|(local, _)| local)
+                .collect();
+            assert_eq!(outs.len(), 1);
+            let out = outs[0];
+            assert!(out.as_usize() < graph.n_locals);
+            assert!(graph.is_connected(Local::from_usize(1), out));
+            assert!(graph.is_connected(Local::from_usize(2), out));
+            assert!(graph.is_connected(out, Local::from_usize(0)));
+        });
+    }
+
+    #[test]
+    fn test_length_queries_of_slice_indexing() {
+        let source = r#"
+            pub fn get(s: &[u32], i: usize) -> u32 {
+                s[i]
+            }
+            pub fn len(v: &Vec<u32>) -> usize {
+                v.len()
+            }
+        "#;
+        with_tcx("length_queries", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            // the bounds check of `s[i]` takes the length of `*s`
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "get"));
+            let queries = graph.length_queries();
+            assert_eq!(queries.len(), 1);
+            let (len, collection) = queries[0];
+            assert!(matches!(graph.nodes[len].ops[0], NodeOp::Len));
+            // the collection is the projection node of `*s`, which is derefed from the parameter
+            assert!(collection.as_usize() >= graph.n_locals);
+            let edge = &graph.edges[graph.nodes[collection].in_edges[0]];
+            assert_eq!(edge.src, Local::from_usize(1));
+            assert!(matches!(edge.op, EdgeOp::Deref));
+            // `Vec::len` is a call rather than `Rvalue::Len`
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "len"));
+            assert!(graph.length_queries().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_arithmetic_chains_of_mul_add() {
+        let source = r#"
+            pub fn mul_add(a: u32, b: u32, c: u32) -> u32 {
+                a * b + c
+            }
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#;
+        with_tcx("arithmetic_chains", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "mul_add"));
+            let chains = graph.arithmetic_chains();
+            assert_eq!(chains.len(), 1);
+            let chain = &chains[0];
+            assert_eq!(chain.len(), 2);
+            // the product flows into the sum, which is returned
+            let bin_op = |local: Local| {
+                graph.nodes[local].ops.iter().find_map(|op| match op {
+                    NodeOp::BinaryOp(bin_op) | NodeOp::CheckedBinaryOp(bin_op) => Some(*bin_op),
+                    _ => None,
+                })
+            };
+            assert!(matches!(
+                bin_op(chain[0]),
+                Some(BinOp::Mul | BinOp::MulWithOverflow)
+            ));
+            assert!(matches!(
+                bin_op(chain[1]),
+                Some(BinOp::Add | BinOp::AddWithOverflow)
+            ));
+            assert!(graph.is_connected(Local::from_usize(1), chain[0]));
+            assert!(graph.is_connected(Local::from_usize(3), chain[1]));
+            assert!(graph.is_connected(chain[1], Local::from_usize(0)));
+            // a single operation is not a chain
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "add"));
+            assert!(graph.arithmetic_chains().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_call_argument_edges_carry_spans() {
+        let source = r#"
+            pub fn first(a: u32, _b: u32) -> u32 {
+                a
+            }
+            pub fn caller(x: u32, y: u32) -> u32 {
+                first(x, y)
+            }
+        "#;
+        with_tcx("call_argument_spans", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "caller"));
+            let args = graph.call_arguments(Local::from_usize(0));
+            assert_eq!(args.len(), 2);
+            let snippets: Vec<String> = graph.nodes[Local::from_usize(0)]
+                .in_edges
+                .iter()
+                .map(|edge_idx| &graph.edges[*edge_idx])
+                .map(|edge| tcx.sess.source_map().span_to_snippet(edge.span).unwrap())
+                .collect();
+            assert_eq!(snippets, vec!["x", "y"]);
+            // the other edges have no spans
+            let edge = &graph.edges[graph.nodes[args[0]].in_edges[0]];
+            assert_eq!(edge.span, DUMMY_SP);
+        });
+    }
+
+    #[test]
+    fn test_assert_of_array_bounds_check() {
+        let source = r#"
+            pub fn get(a: [u32; 4], i: usize) -> u32 {
+                a[i]
+            }
+        "#;
+        with_tcx("assert_bounds_check", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "get"));
+            let assertions = graph.assertions();
+            assert_eq!(assertions.len(), 1);
+            let (cond, msg) = assertions[0];
+            assert!(msg.starts_with("BoundsCheck"));
+            // the condition compares the index with the length of the array
+            assert!(matches!(
+                graph.nodes[cond].ops[0],
+                NodeOp::BinaryOp(BinOp::Lt)
+            ));
+            assert!(graph.is_connected(Local::from_usize(2), cond));
+            let (marker, _) = graph
+                .nodes
+                .iter_enumerated()
+                .find(|(_, node)| matches!(node.ops[0], NodeOp::Assert(_)))
+                .unwrap();
+            assert!(marker.as_usize() >= graph.n_locals);
+            assert!(!graph.nodes[marker].span.is_dummy());
+        });
+    }
+
+    #[test]
+    fn test_call_through_const_fn_pointer() {
+        let source = r#"
+            pub fn double(x: u32) -> u32 {
+                x * 2
+            }
+            const F: fn(u32) -> u32 = double;
+            pub fn call(x: u32) -> u32 {
+                F(x)
+            }
+        "#;
+        with_tcx("const_fn_pointer", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "call"));
+            // `_0 = const F(move _2)`, where the callee is a const rather than a fn item
+            let ret = Local::from_usize(0);
+            assert!(matches!(graph.nodes[ret].ops[0], NodeOp::CallOperand));
+            let args = graph.call_arguments(ret);
+            assert_eq!(args.len(), 2);
+            assert!(matches!(graph.nodes[args[0]].ops[0], NodeOp::Const(_)));
+            assert_eq!(args[1], Local::from_usize(2));
+            assert!(graph.is_connected(Local::from_usize(1), ret));
+        });
+    }
+
+    #[test]
+    fn test_returns_param_directly() {
+        let source = r#"
+            pub fn id<T>(x: T) -> T {
+                x
+            }
+            pub fn second(_x: u32, y: u32) -> u32 {
+                let z = y;
+                z
+            }
+            pub fn inc(x: u32) -> u32 {
+                x.wrapping_add(1)
+            }
+        "#;
+        with_tcx("returns_param_directly", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "id"));
+            assert_eq!(graph.returns_param_directly(), Some(Local::from_usize(1)));
+            // the copies in between are no transformation
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "second"));
+            assert_eq!(graph.returns_param_directly(), Some(Local::from_usize(2)));
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "inc"));
+            assert_eq!(graph.returns_param_directly(), None);
+        });
+    }
+
+    #[test]
+    fn test_op_histogram_counts_calls_and_refs() {
+        let source = r#"
+            pub fn g(x: u32) -> u32 {
+                x
+            }
+            pub fn f(x: u32) -> u32 {
+                let r = &x;
+                g(*r).wrapping_add(0)
+            }
+        "#;
+        with_tcx("op_histogram", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "f"));
+            let histogram = graph.op_histogram();
+            // `g(*r)` and `wrapping_add`
+            assert_eq!(histogram["Call"], 2);
+            assert_eq!(histogram["Ref"], 1);
+            assert!(!histogram.contains_key("Cast"));
+            assert_eq!(
+                histogram.values().sum::<usize>(),
+                graph.nodes.iter().map(|node| node.ops.len()).sum::<usize>()
+            );
+        });
+    }
+
+    #[test]
+    fn test_params_tainting_return_skips_deref() {
+        let source = r#"
+            pub fn add(x: &u32, y: u32) -> u32 {
+                let a = *x;
+                a.wrapping_add(y)
+            }
+        "#;
+        with_tcx("params_tainting_return", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "add"));
+            // `x` only reaches the return value through `*x`
+            assert_eq!(graph.params_tainting_return(), vec![Local::from_usize(2)]);
+            let deps = graph.param_return_deps();
+            assert!(deps[Local::from_usize(1)] && deps[Local::from_usize(2)]);
+        });
+    }
+
+    #[test]
+    fn test_mir_location_of_defining_statement() {
+        let source = r#"
+            pub fn id(x: u32) -> u32 {
+                let y = x;
+                y
+            }
+        "#;
+        with_tcx("mir_location", source, |tcx| {
+            let def_id = def_id_by_name(tcx, "id");
+            let dataflow = DataFlow::new(tcx, false);
+            let body = dataflow.body_of(def_id);
+            let graph = dataflow.build_graph(def_id);
+            // `y` is `_2`, and each local is assigned once
+            for local in [Local::from_usize(0), Local::from_usize(2)] {
+                let location = graph.mir_location(local).unwrap();
+                let statm = &body.basic_blocks[location.block].statements[location.statement_index];
+                match &statm.kind {
+                    StatementKind::Assign(assign) => assert_eq!(assign.0.local, local),
+                    kind => panic!("{:?} is not an assignment", kind),
+                }
+            }
+            // the parameter is written by no statement
+            assert_eq!(graph.mir_location(Local::from_usize(1)), None);
+        });
+    }
+
+    #[test]
+    fn test_common_subexpressions_of_commutative_op() {
+        let source = r#"
+            pub fn twice(a: u32, b: u32) -> u32 {
+                let x = a + b;
+                let y = b + a;
+                let z = a * b;
+                x ^ y ^ z
+            }
+        "#;
+        with_tcx("common_subexpressions", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id_by_name(tcx, "twice"));
+            let groups = graph.common_subexpressions();
+            // the overflow checks of `a + b` and `b + a`, whose operands are copied into temporaries first
+            assert_eq!(groups.len(), 1);
+            assert_eq!(groups[0].len(), 2);
+            for local in groups[0].iter() {
+                assert!(matches!(
+                    graph.nodes[*local].ops[0],
+                    NodeOp::CheckedBinaryOp(BinOp::AddWithOverflow)
+                ));
+            }
+        });
+    }
+
+    #[test]
+    fn test_storage_range_of_scoped_local() {
+        let source = r#"
+            pub fn nested(x: u32) -> u32 {
+                let y = {
+                    let z = x;
+                    z
+                };
+                y
+            }
+        "#;
+        with_tcx("storage_range", source, |tcx| {
+            let def_id = def_id_by_name(tcx, "nested");
+            let mut dataflow = DataFlow::new(tcx, false);
+            let graph = dataflow.build_graph(def_id);
+            assert_eq!(graph.storage_range(Local::from_usize(2)), None);
+            dataflow.record_skipped_statms = true;
+            let graph = dataflow.build_graph(def_id);
+            let body = dataflow.body_of(def_id);
+            let kind_at = |location: Location| {
+                &body.basic_blocks[location.block].statements[location.statement_index].kind
+            };
+            // `y` is `_2` and `z` is `_3`, and `z` dies right after its value is copied into `y`
+            let (y_live, y_dead) = graph.storage_range(Local::from_usize(2)).unwrap();
+            let (z_live, z_dead) = graph.storage_range(Local::from_usize(3)).unwrap();
+            assert!(
+                matches!(kind_at(z_live), StatementKind::StorageLive(local) if local.as_usize() == 3)
+            );
+            assert!(
+                matches!(kind_at(z_dead), StatementKind::StorageDead(local) if local.as_usize() == 3)
+            );
+            assert!(y_live < z_live && z_dead < y_dead);
+            let y_assigned = graph.mir_location(Local::from_usize(2)).unwrap();
+            assert!(z_live < y_assigned && y_assigned < z_dead);
+            // the parameter is always alive
+            assert_eq!(graph.storage_range(Local::from_usize(1)), None);
+        });
+    }
+
+    #[test]
+    fn test_build_graphs_for_crate() {
+        let source = r#"
+            pub const LIMIT: u32 = 7;
+            pub trait Limit {
+                fn limit(&self) -> u32;
+            }
+            pub struct Fixed;
+            impl Limit for Fixed {
+                fn limit(&self) -> u32 {
+                    LIMIT
+                }
+            }
+            pub fn first(x: u32) -> u32 {
+                x
+            }
+            pub fn second(x: u32, y: u32) -> u32 {
+                x ^ y
+            }
+        "#;
+        with_tcx("build_graphs_for_crate", source, |tcx| {
+            let graphs = build_graphs_for_crate(tcx);
+            // the const and the trait method without a default body are skipped
+            let names: HashSet<String> = graphs
+                .keys()
+                .map(|def_id| tcx.def_path_str(*def_id))
+                .collect();
+            assert_eq!(
+                names,
+                HashSet::from([
+                    String::from("first"),
+                    String::from("second"),
+                    String::from("<Fixed as Limit>::limit"),
+                ])
+            );
+            let second = &graphs[&def_id_by_name(tcx, "second")];
+            assert_eq!(second.argc, 2);
+        });
+    }
+
+    #[test]
+    fn test_call_arg_dependencies_of_forwarding_callee() {
+        let source = r#"
+            pub fn pick(a: u32, _b: u32) -> u32 {
+                a
+            }
+            pub fn call(x: u32, y: u32) -> u32 {
+                pick(x, y)
+            }
+        "#;
+        with_tcx("call_arg_dependencies", source, |tcx| {
+            let dataflow = DataFlow::new(tcx, false);
+            let callee = dataflow.build_graph(def_id_by_name(tcx, "pick"));
+            let caller = dataflow.build_graph(def_id_by_name(tcx, "call"));
+            let ret = Local::from_usize(0);
+            // only the first argument flows into the result
+            assert_eq!(
+                caller.call_arg_dependencies(ret, &callee),
+                vec![true, false]
+            );
+            // `_0` is not written by a call to `call`
+            assert!(caller.call_arg_dependencies(ret, &caller).is_empty());
+        });
+    }
+}
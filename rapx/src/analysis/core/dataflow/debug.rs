@@ -1,9 +1,17 @@
+use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::fs::File;
+use std::io::{self, BufWriter, Write as _};
+use std::path::Path;
+#[cfg(feature = "svg")]
+use std::process::{Command, Stdio};
 
-use rustc_middle::mir::Local;
+use rustc_middle::mir::{Local, SourceScope};
 use rustc_middle::ty::TyCtxt;
+use rustc_span::DUMMY_SP;
 
 use super::graph::{AggKind, Graph, GraphEdge, GraphNode, NodeOp};
+use crate::utils::log::span_to_line_number;
 
 fn escaped_string(s: String) -> String {
     s.replace("{", "\\{")
@@ -35,26 +43,28 @@ impl GraphNode {
         local: Local,
         color: Option<String>,
         is_marker: bool,
+        consts: &[String],
     ) -> String {
         let mut attr = String::new();
         let mut dot = String::new();
         if is_marker {
-            // only Nop and Const can be marker node and they only have one op
+            // marker nodes only have one op
             assert!(self.ops.len() == 1);
             match self.ops[0] {
                 NodeOp::Nop => {
                     write!(attr, "label=\"\" style=dashed ").unwrap();
                 }
-                NodeOp::Const(ref name) => {
+                NodeOp::Const(idx) => {
                     write!(
                         attr,
                         "label=\"<f0> {}\" style=dashed ",
-                        escaped_string(name.clone())
+                        escaped_string(consts[idx].clone())
                     )
                     .unwrap();
                 }
                 _ => {
-                    panic!("Wrong arm!");
+                    // the markers created by terminators, e.g., Drop
+                    write!(attr, "style=dashed ").unwrap();
                 }
             }
         }
@@ -124,27 +134,365 @@ impl GraphNode {
 
 impl Graph {
     pub fn to_dot_graph<'tcx>(&self, tcx: &TyCtxt<'tcx>) -> String {
+        let mut dot = Vec::new();
+        // writing into a buffer never fails
+        self.write_dot(tcx, &mut dot).unwrap();
+        String::from_utf8(dot).unwrap()
+    }
+
+    // the params are colored red and the markers are dashed
+    fn node_dot(&self, tcx: &TyCtxt, local: Local) -> String {
+        let node = &self.nodes[local];
+        if local <= Local::from_usize(self.argc) {
+            node.to_dot_graph(tcx, local, Some(String::from("red")), false, &self.consts)
+        } else if local < Local::from_usize(self.n_locals) {
+            node.to_dot_graph(tcx, local, None, false, &self.consts)
+        } else {
+            node.to_dot_graph(tcx, local, None, true, &self.consts)
+        }
+    }
+
+    // Stream the DOT of `to_dot_graph` into the writer line by line,
+    // which avoids holding the whole DOT of a huge graph in memory.
+    pub fn write_dot(&self, tcx: &TyCtxt, writer: &mut impl io::Write) -> io::Result<()> {
+        let name = tcx.def_path_str(self.def_id);
+
+        writeln!(writer, "digraph \"{}\" {{", &name)?;
+        writeln!(writer, "    node [shape=record];")?;
+        for local in self.nodes.indices() {
+            writeln!(writer, "    {}", self.node_dot(tcx, local))?;
+        }
+        //edges
+        for edge in self.edges.iter() {
+            let edge_dot = edge.to_dot_graph();
+            writeln!(writer, "    {}", edge_dot)?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    // Same as `to_dot_graph`, but the nodes are grouped into clusters by their source scopes,
+    // e.g., the locals of a nested block or an inlined function are in their own cluster.
+    pub fn to_dot_graph_clustered(&self, tcx: &TyCtxt) -> String {
         let mut dot = String::new();
         let name = tcx.def_path_str(self.def_id);
 
         writeln!(dot, "digraph \"{}\" {{", &name).unwrap();
         writeln!(dot, "    node [shape=record];").unwrap();
+        // scope idx -> the nodes in the scope
+        let mut clusters: BTreeMap<usize, Vec<Local>> = BTreeMap::new();
         for (local, node) in self.nodes.iter_enumerated() {
-            let node_dot = if local <= Local::from_usize(self.argc) {
-                node.to_dot_graph(tcx, local, Some(String::from("red")), false)
-            } else if local < Local::from_usize(self.n_locals) {
-                node.to_dot_graph(tcx, local, None, false)
-            } else {
-                node.to_dot_graph(tcx, local, None, true)
-            };
-            writeln!(dot, "    {}", node_dot).unwrap();
+            clusters
+                .entry(node.scope.as_usize())
+                .or_default()
+                .push(local);
+        }
+        for (scope, locals) in clusters.iter() {
+            writeln!(dot, "    subgraph cluster_{} {{", scope).unwrap();
+            writeln!(
+                dot,
+                "        label=\"{:?}\";",
+                SourceScope::from_usize(*scope)
+            )
+            .unwrap();
+            for &local in locals.iter() {
+                writeln!(dot, "        {}", self.node_dot(tcx, local)).unwrap();
+            }
+            writeln!(dot, "    }}").unwrap();
         }
-        //edges
         for edge in self.edges.iter() {
-            let edge_dot = edge.to_dot_graph();
-            writeln!(dot, "    {}", edge_dot).unwrap();
+            writeln!(dot, "    {}", edge.to_dot_graph()).unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    pub fn write_dot_to_path(&self, tcx: &TyCtxt, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_dot(tcx, &mut writer)?;
+        writer.flush()
+    }
+
+    // Same as `to_dot_graph`, but the labels of edges and nodes are rendered by the given formatters,
+    // e.g., `|edge| edge.seq.to_string()` to only show the sequence numbers of edges.
+    // The params are still colored red and the markers are still dashed.
+    pub fn to_dot_graph_with_labels<E, N>(
+        &self,
+        tcx: &TyCtxt,
+        edge_label: E,
+        node_label: N,
+    ) -> String
+    where
+        E: Fn(&GraphEdge) -> String,
+        N: Fn(Local, &GraphNode) -> String,
+    {
+        let mut dot = String::new();
+        let name = tcx.def_path_str(self.def_id);
+
+        writeln!(dot, "digraph \"{}\" {{", &name).unwrap();
+        writeln!(dot, "    node [shape=record];").unwrap();
+        for (local, node) in self.nodes.iter_enumerated() {
+            let mut attr = String::new();
+            write!(
+                attr,
+                "label=\"{}\" ",
+                escaped_string(node_label(local, node))
+            )
+            .unwrap();
+            if local <= Local::from_usize(self.argc) {
+                write!(attr, "color=red ").unwrap();
+            } else if local >= Local::from_usize(self.n_locals) {
+                write!(attr, "style=dashed ").unwrap();
+            }
+            writeln!(dot, "    {:?} [{}]", local, attr).unwrap();
+        }
+        for edge in self.edges.iter() {
+            writeln!(
+                dot,
+                "    {:?} -> {:?} [label=\"{}\" ]",
+                edge.src,
+                edge.dst,
+                escaped_string(edge_label(edge))
+            )
+            .unwrap();
         }
         writeln!(dot, "}}").unwrap();
         dot
     }
+
+    // A plain-text listing of the graph for quick inspection in terminals and logs, e.g.,
+    //     _2 (local): Use @ line 3
+    //         <- _1 Copy (seq 0)
+    //         -> _0 Move (seq 0)
+    pub fn dump(&self, tcx: &TyCtxt) -> String {
+        let mut dump = String::new();
+        writeln!(
+            dump,
+            "{}: argc = {}, n_locals = {}",
+            tcx.def_path_str(self.def_id),
+            self.argc,
+            self.n_locals
+        )
+        .unwrap();
+        for (local, node) in self.nodes.iter_enumerated() {
+            let ops: Vec<String> = node
+                .ops
+                .iter()
+                .map(|op| self.node_op_to_string(tcx, op))
+                .collect();
+            write!(
+                dump,
+                "    {:?} ({}): {}",
+                local,
+                self.node_kind_str(local),
+                ops.join(", ")
+            )
+            .unwrap();
+            if node.span != DUMMY_SP {
+                write!(dump, " @ line {}", span_to_line_number(node.span)).unwrap();
+            }
+            writeln!(dump).unwrap();
+            for edge_idx in node.in_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                writeln!(
+                    dump,
+                    "        <- {:?} {:?} (seq {})",
+                    edge.src, edge.op, edge.seq
+                )
+                .unwrap();
+            }
+            for edge_idx in node.out_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                writeln!(
+                    dump,
+                    "        -> {:?} {:?} (seq {})",
+                    edge.dst, edge.op, edge.seq
+                )
+                .unwrap();
+            }
+        }
+        dump
+    }
+
+    // Render the graph into SVG by piping the DOT through the `dot` command of Graphviz.
+    // An error is returned if `dot` is not installed or fails.
+    #[cfg(feature = "svg")]
+    pub fn render_svg(&self, tcx: &TyCtxt) -> io::Result<String> {
+        let dot = self.to_dot_graph(tcx);
+        let mut child = Command::new("dot")
+            .arg("-Tsvg")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(dot.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::core::dataflow::graph::EdgeOp;
+    use crate::analysis::core::dataflow::DataFlow;
+    use crate::utils::test_utils::{def_id_by_name, with_tcx};
+
+    #[cfg(feature = "svg")]
+    const SOURCE: &str = r#"
+        pub fn scale(x: u32) -> u32 {
+            let y = x;
+            y * 7
+        }
+    "#;
+
+    // `_2 = copy _1; _2 = const 7; _0 = move _2;` in the graph of `id`, without mir
+    fn graph_of_id(tcx: TyCtxt<'_>) -> Graph {
+        let mut graph = Graph::new(def_id_by_name(tcx, "id"), DUMMY_SP, 1, 3);
+        graph.add_node_edge(Local::from_usize(1), Local::from_usize(2), EdgeOp::Copy);
+        graph.add_const_edge(7, DUMMY_SP, Local::from_usize(2), EdgeOp::Const);
+        graph.add_node_edge(Local::from_usize(2), Local::from_usize(0), EdgeOp::Move);
+        graph
+    }
+
+    #[test]
+    #[cfg(feature = "svg")]
+    fn test_render_svg() {
+        with_tcx("render_svg", SOURCE, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "scale"));
+            match graph.render_svg(&tcx) {
+                Ok(svg) => {
+                    assert!(svg.contains("<svg"));
+                    assert!(svg.trim_end().ends_with("</svg>"));
+                }
+                // Graphviz is not installed
+                Err(err) => assert_eq!(err.kind(), std::io::ErrorKind::NotFound),
+            }
+        });
+    }
+
+    #[test]
+    fn test_to_dot_graph_with_labels() {
+        with_tcx("dot_labels", "pub fn id(x: u32) -> u32 { x }", |tcx| {
+            let dot = graph_of_id(tcx).to_dot_graph_with_labels(
+                &tcx,
+                |edge| format!("{:?}", edge.op),
+                |local, node| format!("{{{:?}}} x{}", local, node.ops.len()),
+            );
+            assert_eq!(
+                dot,
+                concat!(
+                    "digraph \"id\" {\n",
+                    "    node [shape=record];\n",
+                    "    _0 [label=\"\\{_0\\} x1\" color=red ]\n",
+                    "    _1 [label=\"\\{_1\\} x1\" color=red ]\n",
+                    "    _2 [label=\"\\{_2\\} x1\" ]\n",
+                    "    _3 [label=\"\\{_3\\} x1\" style=dashed ]\n",
+                    "    _1 -> _2 [label=\"Copy\" ]\n",
+                    "    _3 -> _2 [label=\"Const\" ]\n",
+                    "    _2 -> _0 [label=\"Move\" ]\n",
+                    "}\n",
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn test_write_dot() {
+        with_tcx("write_dot", "pub fn id(x: u32) -> u32 { x }", |tcx| {
+            let graph = graph_of_id(tcx);
+            let expected = concat!(
+                "digraph \"id\" {\n",
+                "    node [shape=record];\n",
+                "    _0 [label=\"<f0> _0 \"color=red ]\n",
+                "    _1 [label=\"<f0> _1 \"color=red ]\n",
+                "    _2 [label=\"<f0> _2 \"]\n",
+                "    _3 [label=\"<f0> 7\" style=dashed label=\"<f0> _3 \"]\n",
+                "    _1 -> _2 [label=\"0_Copy\" ]\n",
+                "    _3 -> _2 [label=\"0_Const\" ]\n",
+                "    _2 -> _0 [label=\"0_Move\" ]\n",
+                "}\n",
+            );
+            let mut buffer = Vec::new();
+            graph.write_dot(&tcx, &mut buffer).unwrap();
+            assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+            assert_eq!(graph.to_dot_graph(&tcx), expected);
+            let path =
+                std::env::temp_dir().join(format!("rapx_write_dot_{}.dot", std::process::id()));
+            graph.write_dot_to_path(&tcx, &path).unwrap();
+            let written = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(written, expected);
+        });
+    }
+
+    #[test]
+    fn test_dump() {
+        with_tcx("dump", "pub fn id(x: u32) -> u32 { x }", |tcx| {
+            let mut graph = graph_of_id(tcx);
+            graph.nodes[Local::from_usize(2)].ops[0] = NodeOp::Use;
+            assert_eq!(
+                graph.dump(&tcx),
+                concat!(
+                    "id: argc = 1, n_locals = 3\n",
+                    "    _0 (param): Nop\n",
+                    "        <- _2 Move (seq 0)\n",
+                    "    _1 (param): Nop\n",
+                    "        -> _2 Copy (seq 0)\n",
+                    "    _2 (local): Use\n",
+                    "        <- _1 Copy (seq 0)\n",
+                    "        <- _3 Const (seq 0)\n",
+                    "        -> _0 Move (seq 0)\n",
+                    "    _3 (marker): Const(7)\n",
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn test_to_dot_graph_clustered_by_scope() {
+        let source = r#"
+            pub fn nested(x: u32) -> u32 {
+                let y = {
+                    let z = x;
+                    z
+                };
+                y
+            }
+        "#;
+        with_tcx("dot_clustered", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "nested"));
+            // the body, the scope of `y`, and the nested block where `z` lives
+            assert_eq!(
+                graph.to_dot_graph_clustered(&tcx),
+                concat!(
+                    "digraph \"nested\" {\n",
+                    "    node [shape=record];\n",
+                    "    subgraph cluster_0 {\n",
+                    "        label=\"scope[0]\";\n",
+                    "        _1 [label=\"<f0> _1 \"color=red ]\n",
+                    "        _3 [label=\"<f0> _3 | <f1> (0)Use \"]\n",
+                    "    }\n",
+                    "    subgraph cluster_1 {\n",
+                    "        label=\"scope[1]\";\n",
+                    "        _0 [label=\"<f0> _0 | <f1> (0)Use \"color=red ]\n",
+                    "    }\n",
+                    "    subgraph cluster_2 {\n",
+                    "        label=\"scope[2]\";\n",
+                    "        _2 [label=\"<f0> _2 | <f1> (0)Use \"]\n",
+                    "    }\n",
+                    "    _1 -> _3 [label=\"0_Copy\" ]\n",
+                    "    _3 -> _2 [label=\"0_Copy\" ]\n",
+                    "    _2 -> _0 [label=\"0_Copy\" ]\n",
+                    "}\n",
+                )
+            );
+        });
+    }
 }
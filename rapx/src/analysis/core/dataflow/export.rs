@@ -0,0 +1,320 @@
+use rustc_middle::mir::Local;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::DUMMY_SP;
+use serde_json::{json, Value};
+
+use super::graph::{AggKind, EdgeOp, Graph, NodeOp};
+use crate::utils::log::{span_to_filename, span_to_line_number};
+
+// The version of the JSON schema emitted by `Graph::to_json`.
+// It must be increased whenever the shape of the output changes.
+pub const GRAPH_JSON_SCHEMA: u32 = 1;
+
+impl Graph {
+    // Render an op with the def paths resolved, e.g., `Call(std::mem::drop)` and `Const(1_i32)`.
+    pub fn node_op_to_string(&self, tcx: &TyCtxt, op: &NodeOp) -> String {
+        match op {
+            NodeOp::Const(idx) => format!("Const({})", self.const_value(*idx)),
+            NodeOp::Call(def_id) => format!("Call({})", tcx.def_path_str(def_id)),
+            NodeOp::Aggregate(AggKind::Adt(def_id)) => {
+                format!("Aggregate(Adt({}))", tcx.def_path_str(def_id))
+            }
+            NodeOp::Aggregate(AggKind::Closure(def_id)) => {
+                format!("Aggregate(Closure({}))", tcx.def_path_str(def_id))
+            }
+            NodeOp::Aggregate(AggKind::Coroutine(def_id)) => {
+                format!("Aggregate(Coroutine({}))", tcx.def_path_str(def_id))
+            }
+            _ => format!("{:?}", op),
+        }
+    }
+
+    // Whether the node is a parameter (including the return value `_0`), a mir local, or a marker.
+    pub fn node_kind_str(&self, local: Local) -> &'static str {
+        if local.as_usize() <= self.argc {
+            "param"
+        } else if local.as_usize() < self.n_locals {
+            "local"
+        } else {
+            "marker"
+        }
+    }
+
+    // Export the graph as a JSON object with a stable shape:
+    // {
+    //   "schema": 1, "def_path": "..", "argc": 1, "n_locals": 3,
+    //   "nodes": [{"local": 0, "kind": "param" | "local" | "marker", "ops": [".."], "file": "..", "line": 1}],
+    //   "edges": [{"src": 1, "dst": 0, "op": "..", "seq": 0}]
+    // }
+    // The "file" and "line" of a node are null if its span is unknown.
+    pub fn to_json(&self, tcx: &TyCtxt) -> String {
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter_enumerated()
+            .map(|(local, node)| {
+                let (file, line) = if node.span == DUMMY_SP {
+                    (Value::Null, Value::Null)
+                } else {
+                    (
+                        json!(span_to_filename(node.span)),
+                        json!(span_to_line_number(node.span)),
+                    )
+                };
+                json!({
+                    "local": local.as_usize(),
+                    "kind": self.node_kind_str(local),
+                    "ops": node.ops.iter().map(|op| self.node_op_to_string(tcx, op)).collect::<Vec<_>>(),
+                    "file": file,
+                    "line": line,
+                })
+            })
+            .collect();
+        let edges: Vec<Value> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "src": edge.src.as_usize(),
+                    "dst": edge.dst.as_usize(),
+                    "op": format!("{:?}", edge.op),
+                    "seq": edge.seq,
+                })
+            })
+            .collect();
+        json!({
+            "schema": GRAPH_JSON_SCHEMA,
+            "def_path": tcx.def_path_str(self.def_id),
+            "argc": self.argc,
+            "n_locals": self.n_locals,
+            "nodes": nodes,
+            "edges": edges,
+        })
+        .to_string()
+    }
+
+    // Export the edges as CSV with the header `src_local,dst_local,edge_op,seq`.
+    // The src of a const edge is the synthetic id of the const node.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("src_local,dst_local,edge_op,seq\n");
+        for edge in self.edges.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                edge.src.as_usize(),
+                edge.dst.as_usize(),
+                csv_escaped_string(format!("{:?}", edge.op)),
+                edge.seq
+            ));
+        }
+        csv
+    }
+
+    // Export the graph as a Mermaid flowchart, which can be embedded in Markdown directly.
+    // The markers are drawn as rounded nodes, e.g., `n5(["_5: Drop"])`.
+    pub fn to_mermaid(&self, tcx: &TyCtxt) -> String {
+        let mut mermaid = String::from("flowchart TD\n");
+        for (local, node) in self.nodes.iter_enumerated() {
+            let ops: Vec<String> = node
+                .ops
+                .iter()
+                .map(|op| self.node_op_to_string(tcx, op))
+                .collect();
+            let label = mermaid_escaped_string(format!("{:?}: {}", local, ops.join(", ")));
+            if self.node_kind_str(local) == "marker" {
+                mermaid.push_str(&format!("    n{}([\"{}\"])\n", local.as_usize(), label));
+            } else {
+                mermaid.push_str(&format!("    n{}[\"{}\"]\n", local.as_usize(), label));
+            }
+        }
+        for edge in self.edges.iter() {
+            mermaid.push_str(&format!(
+                "    n{} -->|\"{}\"| n{}\n",
+                edge.src.as_usize(),
+                mermaid_escaped_string(format!("{}_{:?}", edge.seq, edge.op)),
+                edge.dst.as_usize()
+            ));
+        }
+        mermaid
+    }
+
+    // Explain how the value of `from` reaches `to` in prose for the reports, e.g.,
+    //     `_4` is reached from `_1`, which is moved into `_2`, dereferenced, and passed to `std::fs::read` as `_4`.
+    // The path with the fewest edges is narrated edge by edge, and None is returned if `to` is not reachable.
+    pub fn describe_flow(&self, tcx: &TyCtxt, from: Local, to: Local) -> Option<String> {
+        let path = self.shortest_path_by(from, to, |_| 1)?;
+        let mut steps = vec![];
+        for pair in path.windows(2) {
+            let edge = self.outgoing(pair[0]).find(|edge| edge.dst == pair[1])?;
+            let dst = edge.dst;
+            let step = match &edge.op {
+                EdgeOp::Nop => String::from("flows"),
+                EdgeOp::Move => String::from("moved"),
+                EdgeOp::Copy | EdgeOp::CopyForDeref => String::from("copied"),
+                EdgeOp::Const => String::from("used as a constant"),
+                EdgeOp::Immut => String::from("borrowed"),
+                EdgeOp::Mut => String::from("borrowed mutably"),
+                EdgeOp::Deref => String::from("dereferenced"),
+                EdgeOp::Field(field) => format!("projected to the field `{}`", field),
+                EdgeOp::Downcast(variant) => format!("downcast to the variant `{}`", variant),
+                EdgeOp::Index | EdgeOp::ConstIndex => String::from("indexed"),
+                EdgeOp::SubSlice => String::from("sliced"),
+                EdgeOp::SubType => String::from("subtyped"),
+                EdgeOp::UnknownProjection(projection) => format!("projected by `{}`", projection),
+                EdgeOp::SwitchValue(value) => format!("matched against `{}`", value),
+            };
+            // the markers are not named, except the ones where the value ends up
+            let step = match self.nodes[dst].ops.get(edge.seq) {
+                Some(NodeOp::Call(def_id)) => {
+                    format!("passed to `{}` as `{:?}`", tcx.def_path_str(def_id), dst)
+                }
+                Some(NodeOp::Drop) => String::from("dropped"),
+                Some(NodeOp::SwitchDiscr) => String::from("used to branch"),
+                Some(NodeOp::Assert(_)) => String::from("asserted"),
+                _ if self.node_kind_str(dst) != "marker" => format!("{} into `{:?}`", step, dst),
+                _ => step,
+            };
+            steps.push(step);
+        }
+        let mut sentence = format!("`{:?}` is reached from `{:?}`", to, from);
+        match steps.len() {
+            0 => sentence.push_str(" itself"),
+            1 => sentence.push_str(&format!(", which is {}", steps[0])),
+            2 => sentence.push_str(&format!(", which is {} and {}", steps[0], steps[1])),
+            n => sentence.push_str(&format!(
+                ", which is {}, and {}",
+                steps[..n - 1].join(", "),
+                steps[n - 1]
+            )),
+        }
+        sentence.push('.');
+        Some(sentence)
+    }
+}
+
+// quote the field if it contains any special character of CSV
+fn csv_escaped_string(s: String) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+// replace the characters breaking a quoted Mermaid label with entity codes
+fn mermaid_escaped_string(s: String) -> String {
+    s.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+        .replace('|', "#124;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::core::dataflow::DataFlow;
+    use crate::utils::test_utils::{def_id_by_name, with_tcx};
+    use rustc_hir::def_id::CRATE_DEF_ID;
+
+    #[test]
+    fn test_to_json_schema() {
+        let source = "pub fn id(x: u32) -> u32 {\n    x\n}\n";
+        with_tcx("to_json", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "id"));
+            let json: Value = serde_json::from_str(&graph.to_json(&tcx)).unwrap();
+            assert_eq!(json["schema"], json!(GRAPH_JSON_SCHEMA));
+            assert_eq!(json["def_path"], json!("id"));
+            assert_eq!(json["argc"], json!(1));
+            assert_eq!(json["n_locals"], json!(2));
+            assert_eq!(
+                json["edges"],
+                json!([{"src": 1, "dst": 0, "op": "Copy", "seq": 0}])
+            );
+            let ret = &json["nodes"][0];
+            assert_eq!(ret["local"], json!(0));
+            assert_eq!(ret["kind"], json!("param"));
+            assert_eq!(ret["ops"], json!(["Use"]));
+            assert!(ret["file"].as_str().unwrap().ends_with("lib.rs"));
+            assert_eq!(ret["line"], json!(2));
+            // the parameter is never written, so its span is unknown
+            assert_eq!(json["nodes"][1]["line"], Value::Null);
+        });
+    }
+
+    #[test]
+    fn test_to_csv_escapes_ops() {
+        // _0 = copy (_1.<a,b>); _1 = const 5;
+        let mut graph = Graph::new(CRATE_DEF_ID.to_def_id(), DUMMY_SP, 1, 2);
+        let field = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(
+            Local::from_usize(1),
+            field,
+            EdgeOp::Field(String::from("a,b")),
+        );
+        graph.add_node_edge(field, Local::from_usize(0), EdgeOp::Copy);
+        graph.add_const_edge(5, DUMMY_SP, Local::from_usize(1), EdgeOp::Const);
+        assert_eq!(
+            graph.to_csv(),
+            "src_local,dst_local,edge_op,seq\n\
+             1,2,\"Field(\"\"a,b\"\")\",0\n\
+             2,0,Copy,0\n\
+             3,1,Const,0\n"
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        with_tcx("to_mermaid", "pub fn id(x: u32) -> u32 { x }", |tcx| {
+            // _2 = copy _1; _2 = const "a|b"; _0 = move _2;
+            let mut graph = Graph::new(def_id_by_name(tcx, "id"), DUMMY_SP, 1, 3);
+            graph.add_node_edge(Local::from_usize(1), Local::from_usize(2), EdgeOp::Copy);
+            graph.add_const_edge("a|b", DUMMY_SP, Local::from_usize(2), EdgeOp::Const);
+            graph.add_node_edge(Local::from_usize(2), Local::from_usize(0), EdgeOp::Move);
+            assert_eq!(
+                graph.to_mermaid(&tcx),
+                concat!(
+                    "flowchart TD\n",
+                    "    n0[\"_0: Nop\"]\n",
+                    "    n1[\"_1: Nop\"]\n",
+                    "    n2[\"_2: Nop\"]\n",
+                    "    n3([\"_3: Const(a#124;b)\"])\n",
+                    "    n1 -->|\"0_Copy\"| n2\n",
+                    "    n3 -->|\"0_Const\"| n2\n",
+                    "    n2 -->|\"0_Move\"| n0\n",
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn test_describe_flow() {
+        let source = r#"
+            pub fn consume(x: u32) -> u32 {
+                x
+            }
+            pub fn forward(r: &u32) -> u32 {
+                let a = *r;
+                consume(a)
+            }
+        "#;
+        with_tcx("describe_flow", source, |tcx| {
+            let graph = DataFlow::new(tcx, false).build_graph(def_id_by_name(tcx, "forward"));
+            // `_2 = copy (*_1); _3 = copy _2; _0 = consume(move _3)`, where the temporary is moved into the call
+            assert_eq!(
+                graph
+                    .describe_flow(&tcx, Local::from_usize(1), Local::from_usize(0))
+                    .unwrap(),
+                "`_0` is reached from `_1`, which is dereferenced, copied into `_2`, copied into `_3`, \
+                 and passed to `consume` as `_0`."
+            );
+            assert_eq!(
+                graph
+                    .describe_flow(&tcx, Local::from_usize(2), Local::from_usize(2))
+                    .unwrap(),
+                "`_2` is reached from `_2` itself."
+            );
+            assert_eq!(
+                graph.describe_flow(&tcx, Local::from_usize(0), Local::from_usize(1)),
+                None
+            );
+        });
+    }
+}
@@ -1,21 +1,35 @@
-use std::cell::Cell;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fmt::Write as _;
+use std::hash::{Hash, Hasher};
+use std::ops::Index;
 
+#[cfg(feature = "petgraph")]
+use petgraph::graph::{DiGraph, NodeIndex};
 use rustc_hir::def_id::DefId;
 use rustc_index::IndexVec;
 use rustc_middle::mir::{
-    AggregateKind, BorrowKind, Const, Local, Operand, Place, PlaceElem, Rvalue, Statement,
-    StatementKind, Terminator, TerminatorKind,
+    AggregateKind, BinOp, BorrowKind, Const, InlineAsmOperand, Local, Location, Operand, Place,
+    PlaceElem, ProjectionElem, ProjectionKind, Rvalue, SourceScope, Statement, StatementKind,
+    Terminator, TerminatorKind, OUTERMOST_SOURCE_SCOPE,
 };
 use rustc_middle::ty::TyKind;
-use rustc_span::{Span, DUMMY_SP};
+use rustc_span::source_map::Spanned;
+use rustc_span::{BytePos, Span, DUMMY_SP};
 
-#[derive(Clone, Debug)]
+use crate::analysis::core::graph::{dfs, dfs_with, is_reachable, DirectedGraph};
+pub use crate::analysis::core::graph::{DFSStatus, Direction};
+use crate::rap_warn;
+use crate::utils::log::span_to_string;
+
+#[derive(Clone, Debug, Hash)]
 pub enum NodeOp {
     //warning: the fields are related to the version of the backend rustc version
     Nop,
     Err,
-    Const(String),
+    Const(ConstIdx), // the interned const value, see `Graph::consts`
     //Rvalue
     Use,
     Repeat,
@@ -24,8 +38,8 @@ pub enum NodeOp {
     AddressOf,
     Len,
     Cast,
-    BinaryOp,
-    CheckedBinaryOp,
+    BinaryOp(BinOp),
+    CheckedBinaryOp(BinOp), // the ops returning (result, overflowed), e.g., `AddWithOverflow`
     NullaryOp,
     UnaryOp,
     Discriminant,
@@ -33,18 +47,63 @@ pub enum NodeOp {
     ShallowInitBox,
     CopyForDeref,
     RawPtr,
+    //StatementKind
+    SetDiscriminant,
     //TerminatorKind
     Call(DefId),
-    CallOperand, // the first in_edge is the func
+    CallOperand,         // the first in_edge is the func
+    Drop,                // a marker node whose only in_edge is the dropped place
+    SwitchDiscr,         // a marker node whose only in_edge is the discriminant of a branch
+    InlineAsm,           // every output of an `asm!` depends on all of its inputs
+    SwitchBranch(usize), // a marker node for the target block of a branch, whose in_edge carries the matched value
+    Assert(String), // a marker node whose only in_edge is the checked condition, with what is checked, e.g., BoundsCheck
+    Yield, // a marker node whose only in_edge is the yielded value, or the resume argument written by the resumer
 }
 
-#[derive(Clone, Debug)]
+impl NodeOp {
+    // the name of the variant without the fields, e.g., for statistics across functions
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeOp::Nop => "Nop",
+            NodeOp::Err => "Err",
+            NodeOp::Const(_) => "Const",
+            NodeOp::Use => "Use",
+            NodeOp::Repeat => "Repeat",
+            NodeOp::Ref => "Ref",
+            NodeOp::ThreadLocalRef => "ThreadLocalRef",
+            NodeOp::AddressOf => "AddressOf",
+            NodeOp::Len => "Len",
+            NodeOp::Cast => "Cast",
+            NodeOp::BinaryOp(_) => "BinaryOp",
+            NodeOp::CheckedBinaryOp(_) => "CheckedBinaryOp",
+            NodeOp::NullaryOp => "NullaryOp",
+            NodeOp::UnaryOp => "UnaryOp",
+            NodeOp::Discriminant => "Discriminant",
+            NodeOp::Aggregate(_) => "Aggregate",
+            NodeOp::ShallowInitBox => "ShallowInitBox",
+            NodeOp::CopyForDeref => "CopyForDeref",
+            NodeOp::RawPtr => "RawPtr",
+            NodeOp::SetDiscriminant => "SetDiscriminant",
+            NodeOp::Call(_) => "Call",
+            NodeOp::CallOperand => "CallOperand",
+            NodeOp::Drop => "Drop",
+            NodeOp::SwitchDiscr => "SwitchDiscr",
+            NodeOp::InlineAsm => "InlineAsm",
+            NodeOp::SwitchBranch(_) => "SwitchBranch",
+            NodeOp::Assert(_) => "Assert",
+            NodeOp::Yield => "Yield",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum EdgeOp {
     Nop,
     //Operand
     Move,
     Copy,
     Const,
+    CopyForDeref, // a transparent copy of the place to be dereferenced
     //Mutability
     Immut,
     Mut,
@@ -56,6 +115,47 @@ pub enum EdgeOp {
     ConstIndex,
     SubSlice,
     SubType,
+    UnknownProjection(String), // the projections unsupported yet, e.g., those added by newer rustc versions
+    //Branch
+    SwitchValue(u128), // the discriminant equals the value on the branch
+}
+
+// The statements other than assignments do not build edges,
+// but they can be recorded on the related locals for liveness-aware analyses.
+#[derive(Clone, Copy, Debug)]
+pub enum SkippedStatm {
+    FakeRead,
+    Deinit,
+    StorageLive,
+    StorageDead,
+    Retag,
+    PlaceMention,
+    AscribeUserType,
+}
+
+// The constructs that the builder cannot model yet, which are recorded instead of aborting the analysis.
+// The graph is still built, where the unsupported parts are replaced by `NodeOp::Err` or `EdgeOp::UnknownProjection`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphBuildError {
+    UnsupportedAggregate(String),  // the statement
+    UnsupportedProjection(String), // the place elem
+}
+
+impl fmt::Display for GraphBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphBuildError::UnsupportedAggregate(statement) => {
+                write!(
+                    f,
+                    "Unsupported aggregate kind in dataflow graph: {}",
+                    statement
+                )
+            }
+            GraphBuildError::UnsupportedProjection(place_elem) => {
+                write!(f, "Unsupported projection {} in dataflow graph", place_elem)
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -64,6 +164,7 @@ pub struct GraphEdge {
     pub dst: Local,
     pub op: EdgeOp,
     pub seq: usize,
+    pub span: Span, //the span of the operand, only recorded for the arguments of calls yet
 }
 
 #[derive(Clone)]
@@ -73,6 +174,11 @@ pub struct GraphNode {
     pub seq: usize, //the sequence number, edges with the same seq number are added in the same batch within a statement or terminator
     pub out_edges: Vec<EdgeIdx>,
     pub in_edges: Vec<EdgeIdx>,
+    pub skipped_statms: Vec<SkippedStatm>, //only recorded if the builder asks for it
+    pub storage_statms: Vec<(SkippedStatm, Location)>, //where the StorageLive and StorageDead are, recorded along with `skipped_statms`
+    pub global_seq: Vec<usize>, //the global sequence number of each batch, which orders the writes across the whole body
+    pub scope: SourceScope, //the source scope of the last write, e.g., a nested block or an inlined function
+    pub location: Option<Location>, //the mir statement or terminator of the last write, None if never written
 }
 
 impl GraphNode {
@@ -83,13 +189,21 @@ impl GraphNode {
             seq: 0,
             out_edges: vec![],
             in_edges: vec![],
+            skipped_statms: vec![],
+            storage_statms: vec![],
+            global_seq: vec![],
+            scope: OUTERMOST_SOURCE_SCOPE,
+            location: None,
         }
     }
 }
 
 pub type EdgeIdx = usize;
+pub type ConstIdx = usize;
+pub type ProjectionKey = (Local, Vec<ProjectionKind>); //a place prefix, see `Graph::projection_key`
 pub type GraphNodes = IndexVec<Local, GraphNode>;
 pub type GraphEdges = IndexVec<EdgeIdx, GraphEdge>;
+#[derive(Clone)]
 pub struct Graph {
     pub def_id: DefId,
     pub span: Span,
@@ -97,68 +211,246 @@ pub struct Graph {
     pub nodes: GraphNodes, //constsis of locals in mir and newly created markers
     pub edges: GraphEdges,
     pub n_locals: usize,
+    pub consts: Vec<String>, //the interned const values, indexed by ConstIdx
+    const_registry: HashMap<u64, Vec<ConstIdx>>, // the hash of a const value -> the idxs of the values with the hash
+    const_buf: String, //reused to format the const values before interning them
+    global_seq: usize, //the number of statements and terminators added so far
+    pub errors: Vec<GraphBuildError>, //the unsupported constructs met while building, in the order they are met
+    scope: SourceScope,               //the source scope of the statement or terminator being added
+    // the tables keyed by locals must be renumbered in `apply_remap` together with the nodes
+    field_names: HashMap<ProjectionKey, String>, //the resolved names of the field projections, see `set_field_names`
+    ref_locals: HashSet<Local>, //the locals of reference types, see `set_ref_locals`
+    location: Option<Location>, //the location of the statement or terminator being added, see `set_location`
 }
 
 impl Graph {
     pub fn new(def_id: DefId, span: Span, argc: usize, n_locals: usize) -> Self {
+        Self::with_capacity(def_id, span, argc, n_locals, 0)
+    }
+
+    // reserve the storage of edges in advance to avoid reallocations for large bodies
+    pub fn with_capacity(
+        def_id: DefId,
+        span: Span,
+        argc: usize,
+        n_locals: usize,
+        edge_cap: usize,
+    ) -> Self {
         Self {
             def_id,
             span,
             argc,
             nodes: GraphNodes::from_elem_n(GraphNode::new(), n_locals),
-            edges: GraphEdges::new(),
+            edges: GraphEdges::with_capacity(edge_cap),
             n_locals,
+            consts: Vec::new(),
+            const_registry: HashMap::new(),
+            const_buf: String::new(),
+            global_seq: 0,
+            errors: Vec::new(),
+            scope: OUTERMOST_SOURCE_SCOPE,
+            field_names: HashMap::new(),
+            ref_locals: HashSet::new(),
+            location: None,
+        }
+    }
+
+    // the parameters are _1, _2, ..., _argc
+    pub fn params(&self) -> impl Iterator<Item = Local> {
+        (1..self.argc + 1).map(Local::from_usize)
+    }
+
+    // the return value is always _0
+    pub fn return_local(&self) -> Local {
+        Local::from_usize(0)
+    }
+
+    // identical const values share one entry in `consts`, which is the only copy of the value
+    pub fn intern_const(&mut self, value: &str) -> ConstIdx {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let idxs = self.const_registry.entry(hasher.finish()).or_default();
+        if let Some(&idx) = idxs.iter().find(|idx| self.consts[**idx] == value) {
+            return idx;
         }
+        let idx = self.consts.len();
+        self.consts.push(value.to_owned());
+        idxs.push(idx);
+        idx
+    }
+
+    pub fn const_value(&self, idx: ConstIdx) -> &str {
+        &self.consts[idx]
+    }
+
+    // add a marker node which is not a local in mir, e.g., the node representing a drop
+    pub fn add_marker_node(&mut self, op: NodeOp, span: Span) -> Local {
+        let mut node = GraphNode::new();
+        node.ops[0] = op;
+        node.span = span;
+        node.scope = self.scope;
+        node.location = self.location;
+        node.global_seq.push(self.global_seq);
+        self.nodes.push(node)
     }
 
     // add an edge into an existing node
     pub fn add_node_edge(&mut self, src: Local, dst: Local, op: EdgeOp) -> EdgeIdx {
         let seq = self.nodes[dst].seq;
-        let edge_idx = self.edges.push(GraphEdge { src, dst, op, seq });
+        let edge_idx = self.edges.push(GraphEdge {
+            src,
+            dst,
+            op,
+            seq,
+            span: DUMMY_SP,
+        });
         self.nodes[dst].in_edges.push(edge_idx);
         self.nodes[src].out_edges.push(edge_idx);
         edge_idx
     }
 
     // add an edge into an existing node with const value as src
-    pub fn add_const_edge(&mut self, src: String, dst: Local, op: EdgeOp) -> EdgeIdx {
+    // the span is where the const value is written, which is kept by the const node
+    // the value is formatted into a reused buffer, so only the new values are allocated
+    pub fn add_const_edge(
+        &mut self,
+        src: impl fmt::Display,
+        span: Span,
+        dst: Local,
+        op: EdgeOp,
+    ) -> EdgeIdx {
         let seq = self.nodes[dst].seq;
+        let mut const_buf = std::mem::take(&mut self.const_buf);
+        const_buf.clear();
+        write!(const_buf, "{}", src).unwrap();
+        let const_idx = self.intern_const(&const_buf);
+        self.const_buf = const_buf;
         let mut const_node = GraphNode::new();
-        const_node.ops[0] = NodeOp::Const(src);
+        const_node.ops[0] = NodeOp::Const(const_idx);
+        const_node.span = span;
+        const_node.scope = self.scope;
+        const_node.location = self.location;
         let src = self.nodes.push(const_node);
-        let edge_idx = self.edges.push(GraphEdge { src, dst, op, seq });
+        let edge_idx = self.edges.push(GraphEdge {
+            src,
+            dst,
+            op,
+            seq,
+            span: DUMMY_SP,
+        });
         self.nodes[dst].in_edges.push(edge_idx);
         edge_idx
     }
 
-    pub fn add_operand(&mut self, operand: &Operand, dst: Local) {
+    pub fn add_operand(&mut self, operand: &Operand, dst: Local) -> EdgeIdx {
         match operand {
             Operand::Copy(place) => {
                 let src = self.parse_place(place);
-                self.add_node_edge(src, dst, EdgeOp::Copy);
+                self.add_node_edge(src, dst, EdgeOp::Copy)
             }
             Operand::Move(place) => {
                 let src = self.parse_place(place);
-                self.add_node_edge(src, dst, EdgeOp::Move);
-            }
-            Operand::Constant(boxed_const_op) => {
-                self.add_const_edge(boxed_const_op.const_.to_string(), dst, EdgeOp::Const);
+                self.add_node_edge(src, dst, EdgeOp::Move)
             }
+            Operand::Constant(boxed_const_op) => self.add_const_edge(
+                boxed_const_op.const_,
+                boxed_const_op.span,
+                dst,
+                EdgeOp::Const,
+            ),
         }
     }
 
+    // the span of each argument is kept on its edge, e.g., to report which argument flows into the call
+    fn add_call_argument(&mut self, arg: &Spanned<Operand>, dst: Local) {
+        //rustc version related
+        let edge_idx = self.add_operand(&arg.node, dst);
+        self.edges[edge_idx].span = arg.span;
+    }
+
+    // The graph has no access to the types, so the names of the fields are resolved by the builder in advance,
+    // e.g., `x` instead of `0` for `(*_1).x`. The fields without names are still formatted by their indices.
+    pub fn set_field_names(&mut self, field_names: HashMap<ProjectionKey, String>) {
+        self.field_names = field_names;
+    }
+
+    // The types of the locals are unknown to the graph as well, so the builder records those of reference types.
+    pub fn set_ref_locals(&mut self, ref_locals: HashSet<Local>) {
+        self.ref_locals = ref_locals;
+    }
+
+    pub fn is_ref_local(&self, local: Local) -> bool {
+        self.ref_locals.contains(&local)
+    }
+
+    // identify a place prefix ending with a field projection, where the types in the projection are erased
+    // since they are decided by the local and the projection before them anyway
+    pub fn projection_key(local: Local, projection: &[PlaceElem]) -> ProjectionKey {
+        let projection = projection
+            .iter()
+            .map(|place_elem| match *place_elem {
+                PlaceElem::Deref => ProjectionElem::Deref,
+                PlaceElem::Field(field_idx, _) => ProjectionElem::Field(field_idx, ()),
+                PlaceElem::Index(_) => ProjectionElem::Index(()),
+                PlaceElem::ConstantIndex {
+                    offset,
+                    min_length,
+                    from_end,
+                } => ProjectionElem::ConstantIndex {
+                    offset,
+                    min_length,
+                    from_end,
+                },
+                PlaceElem::Subslice { from, to, from_end } => {
+                    ProjectionElem::Subslice { from, to, from_end }
+                }
+                PlaceElem::Downcast(symbol, variant_idx) => {
+                    ProjectionElem::Downcast(symbol, variant_idx)
+                }
+                PlaceElem::OpaqueCast(_) => ProjectionElem::OpaqueCast(()),
+                PlaceElem::Subtype(_) => ProjectionElem::Subtype(()),
+            })
+            .collect();
+        (local, projection)
+    }
+
+    // The statements and terminators do not know where they are, so the builder tells the graph before adding each of them.
+    // The nodes written afterwards record the location, see `mir_location`.
+    pub fn set_location(&mut self, location: Location) {
+        self.location = Some(location);
+    }
+
+    // The mir statement or terminator which wrote the node last, e.g., for the diagnostics pointing to mir.
+    pub fn mir_location(&self, local: Local) -> Option<Location> {
+        self.nodes[local].location
+    }
+
     pub fn parse_place(&mut self, place: &Place) -> Local {
-        fn parse_one_step(graph: &mut Graph, src: Local, place_elem: PlaceElem) -> Local {
-            let dst = graph.nodes.push(GraphNode::new());
+        fn parse_one_step(
+            graph: &mut Graph,
+            src: Local,
+            place_elem: PlaceElem,
+            field_name: Option<String>,
+        ) -> Local {
+            let mut node = GraphNode::new();
+            node.scope = graph.scope;
+            node.location = graph.location;
+            let dst = graph.nodes.push(node);
             match place_elem {
                 PlaceElem::Deref => {
                     graph.add_node_edge(src, dst, EdgeOp::Deref);
                 }
                 PlaceElem::Field(field_idx, _) => {
-                    graph.add_node_edge(src, dst, EdgeOp::Field(format!("{:?}", field_idx)));
+                    let field = field_name.unwrap_or_else(|| format!("{:?}", field_idx));
+                    graph.add_node_edge(src, dst, EdgeOp::Field(field));
                 }
-                PlaceElem::Downcast(symbol, _) => {
-                    graph.add_node_edge(src, dst, EdgeOp::Downcast(symbol.unwrap().to_string()));
+                PlaceElem::Downcast(symbol, variant_idx) => {
+                    // the variants of coroutines have no names
+                    let variant = match symbol {
+                        Some(symbol) => symbol.to_string(),
+                        None => format!("{:?}", variant_idx),
+                    };
+                    graph.add_node_edge(src, dst, EdgeOp::Downcast(variant));
                 }
                 PlaceElem::Index(idx) => {
                     graph.add_node_edge(src, dst, EdgeOp::Index);
@@ -174,25 +466,42 @@ impl Graph {
                     graph.add_node_edge(src, dst, EdgeOp::SubType);
                 }
                 _ => {
-                    println!("{:?}", place_elem);
-                    todo!()
+                    // keep the graph partial but usable
+                    let error = GraphBuildError::UnsupportedProjection(format!("{:?}", place_elem));
+                    rap_warn!("{}", error);
+                    graph.errors.push(error);
+                    graph.add_node_edge(
+                        src,
+                        dst,
+                        EdgeOp::UnknownProjection(format!("{:?}", place_elem)),
+                    );
                 }
             }
             dst
         }
         let mut ret = place.local;
-        for place_elem in place.projection {
+        for (i, place_elem) in place.projection.iter().enumerate() {
             // if there are projections, then add marker nodes
-            ret = parse_one_step(self, ret, place_elem);
+            let field_name = match place_elem {
+                PlaceElem::Field(..) => self
+                    .field_names
+                    .get(&Self::projection_key(place.local, &place.projection[..=i]))
+                    .cloned(),
+                _ => None,
+            };
+            ret = parse_one_step(self, ret, place_elem, field_name);
         }
         ret
     }
 
     pub fn add_statm_to_graph(&mut self, statement: &Statement) {
+        self.scope = statement.source_info.scope;
         if let StatementKind::Assign(boxed_statm) = &statement.kind {
             let place = boxed_statm.0;
             let dst = self.parse_place(&place);
             self.nodes[dst].span = statement.source_info.span;
+            self.nodes[dst].scope = self.scope;
+            self.nodes[dst].location = self.location;
             let rvalue = &boxed_statm.1;
             let seq = self.nodes[dst].seq;
             if seq == self.nodes[dst].ops.len() {
@@ -227,10 +536,15 @@ impl Graph {
                     self.add_operand(operand, dst);
                     self.nodes[dst].ops[seq] = NodeOp::Cast;
                 }
-                Rvalue::BinaryOp(_, operands) => {
+                Rvalue::BinaryOp(bin_op, operands) => {
                     self.add_operand(&operands.0, dst);
                     self.add_operand(&operands.1, dst);
-                    self.nodes[dst].ops[seq] = NodeOp::CheckedBinaryOp;
+                    self.nodes[dst].ops[seq] = match bin_op {
+                        BinOp::AddWithOverflow
+                        | BinOp::SubWithOverflow
+                        | BinOp::MulWithOverflow => NodeOp::CheckedBinaryOp(*bin_op),
+                        _ => NodeOp::BinaryOp(*bin_op),
+                    };
                 }
                 Rvalue::Aggregate(boxed_kind, operands) => {
                     for operand in operands.iter() {
@@ -253,8 +567,12 @@ impl Graph {
                             self.nodes[dst].ops[seq] = NodeOp::Aggregate(AggKind::Coroutine(def_id))
                         }
                         _ => {
-                            println!("{:?}", statement);
-                            todo!()
+                            // keep building a partial graph instead of aborting the analysis
+                            let error =
+                                GraphBuildError::UnsupportedAggregate(format!("{:?}", statement));
+                            rap_warn!("{}", error);
+                            self.errors.push(error);
+                            self.nodes[dst].ops[seq] = NodeOp::Err;
                         }
                     }
                 }
@@ -263,11 +581,11 @@ impl Graph {
                     self.nodes[dst].ops[seq] = NodeOp::UnaryOp;
                 }
                 Rvalue::NullaryOp(_, ty) => {
-                    self.add_const_edge(ty.to_string(), dst, EdgeOp::Nop);
+                    self.add_const_edge(ty, statement.source_info.span, dst, EdgeOp::Nop);
                     self.nodes[dst].ops[seq] = NodeOp::NullaryOp;
                 }
                 Rvalue::ThreadLocalRef(_) => {
-                    todo!()
+                    self.nodes[dst].ops[seq] = NodeOp::ThreadLocalRef;
                 }
                 Rvalue::Discriminant(place) => {
                     let src = self.parse_place(place);
@@ -280,7 +598,7 @@ impl Graph {
                 }
                 Rvalue::CopyForDeref(place) => {
                     let src = self.parse_place(place);
-                    self.add_node_edge(src, dst, EdgeOp::Nop);
+                    self.add_node_edge(src, dst, EdgeOp::CopyForDeref);
                     self.nodes[dst].ops[seq] = NodeOp::CopyForDeref;
                 }
                 Rvalue::RawPtr(_, place) => {
@@ -290,50 +608,255 @@ impl Graph {
                 }
             };
             self.nodes[dst].seq = seq + 1;
-        }
-    }
-
-    pub fn add_terminator_to_graph(&mut self, terminator: &Terminator) {
-        if let TerminatorKind::Call {
-            func,
-            args,
-            destination,
-            ..
-        } = &terminator.kind
+            self.nodes[dst].global_seq.push(self.global_seq);
+        } else if let StatementKind::SetDiscriminant {
+            place,
+            variant_index,
+        } = &statement.kind
         {
-            let dst = destination.local;
+            // the variant written into the place is encoded as a const edge
+            let dst = self.parse_place(place);
+            self.nodes[dst].span = statement.source_info.span;
+            self.nodes[dst].scope = self.scope;
+            self.nodes[dst].location = self.location;
             let seq = self.nodes[dst].seq;
             if seq == self.nodes[dst].ops.len() {
                 self.nodes[dst].ops.push(NodeOp::Nop);
             }
-            match func {
-                Operand::Constant(boxed_cnst) => {
-                    if let Const::Val(_, ty) = boxed_cnst.const_ {
-                        if let TyKind::FnDef(def_id, _) = ty.kind() {
-                            for op in args.iter() {
-                                //rustc version related
-                                self.add_operand(&op.node, dst);
+            self.add_const_edge(
+                variant_index.as_usize(),
+                statement.source_info.span,
+                dst,
+                EdgeOp::Const,
+            );
+            self.nodes[dst].ops[seq] = NodeOp::SetDiscriminant;
+            self.nodes[dst].seq = seq + 1;
+            self.nodes[dst].global_seq.push(self.global_seq);
+        }
+        self.global_seq += 1;
+    }
+
+    // Record the statements that do not contribute to dataflow on the locals they refer to.
+    // Projections are ignored so that no marker node is created.
+    pub fn add_skipped_statm_to_graph(&mut self, statement: &Statement) {
+        let (local, skipped_statm) = match &statement.kind {
+            StatementKind::FakeRead(box (_, place)) => (place.local, SkippedStatm::FakeRead),
+            StatementKind::Deinit(place) => (place.local, SkippedStatm::Deinit),
+            StatementKind::StorageLive(local) => (*local, SkippedStatm::StorageLive),
+            StatementKind::StorageDead(local) => (*local, SkippedStatm::StorageDead),
+            StatementKind::Retag(_, place) => (place.local, SkippedStatm::Retag),
+            StatementKind::PlaceMention(place) => (place.local, SkippedStatm::PlaceMention),
+            StatementKind::AscribeUserType(box (place, _), _) => {
+                (place.local, SkippedStatm::AscribeUserType)
+            }
+            _ => return,
+        };
+        self.nodes[local].skipped_statms.push(skipped_statm);
+        if let (SkippedStatm::StorageLive | SkippedStatm::StorageDead, Some(location)) =
+            (skipped_statm, self.location)
+        {
+            self.nodes[local]
+                .storage_statms
+                .push((skipped_statm, location));
+        }
+    }
+
+    // The range where the local is alive, from its first `StorageLive` to its last `StorageDead` in the order
+    // of basic blocks, which is only available if the builder records the skipped statements.
+    // None if either of them is missing, e.g., for the parameters and the return value, which are always alive.
+    pub fn storage_range(&self, local: Local) -> Option<(Location, Location)> {
+        let storage_statms = &self.nodes[local].storage_statms;
+        let live = storage_statms
+            .iter()
+            .find(|(statm, _)| matches!(statm, SkippedStatm::StorageLive))?;
+        let dead = storage_statms
+            .iter()
+            .rev()
+            .find(|(statm, _)| matches!(statm, SkippedStatm::StorageDead))?;
+        Some((live.1, dead.1))
+    }
+
+    // Record the value matched by each branch of a `SwitchInt` as an edge from the discriminant
+    // to a marker of the target block, e.g., the discriminant equals 0 on the branch to bb1.
+    // The marker of the discriminant is the one returned by `add_terminator_to_graph` for the terminator.
+    // The otherwise branch is not recorded since it matches no single value.
+    pub fn add_switch_targets_to_graph(&mut self, terminator: &Terminator, discr: Local) {
+        if let TerminatorKind::SwitchInt { targets, .. } = &terminator.kind {
+            let global_seq = self.nodes[discr].global_seq.clone();
+            for (value, target) in targets.iter() {
+                let dst = self.add_marker_node(
+                    NodeOp::SwitchBranch(target.as_usize()),
+                    terminator.source_info.span,
+                );
+                // the branches belong to the same terminator as the discriminant
+                self.nodes[dst].global_seq = global_seq.clone();
+                self.add_node_edge(discr, dst, EdgeOp::SwitchValue(value));
+            }
+        }
+    }
+
+    // Returns the marker of the discriminant if the terminator is a `SwitchInt`.
+    pub fn add_terminator_to_graph(&mut self, terminator: &Terminator) -> Option<Local> {
+        self.scope = terminator.source_info.scope;
+        let mut switch_discr = None;
+        match &terminator.kind {
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                ..
+            } => {
+                let dst = destination.local;
+                let seq = self.nodes[dst].seq;
+                if seq == self.nodes[dst].ops.len() {
+                    self.nodes[dst].ops.push(NodeOp::Nop);
+                }
+                match func {
+                    Operand::Constant(boxed_cnst) => {
+                        let callee_def_id = match boxed_cnst.const_ {
+                            Const::Val(_, ty) => match ty.kind() {
+                                TyKind::FnDef(def_id, _) => Some(*def_id),
+                                _ => None,
+                            },
+                            _ => None,
+                        };
+                        match callee_def_id {
+                            Some(def_id) => {
+                                for arg in args.iter() {
+                                    self.add_call_argument(arg, dst);
+                                }
+                                self.nodes[dst].ops[seq] = NodeOp::Call(def_id);
+                            }
+                            None => {
+                                // e.g., a const fn pointer, which is called like a place
+                                self.add_operand(func, dst);
+                                for arg in args.iter() {
+                                    self.add_call_argument(arg, dst);
+                                }
+                                self.nodes[dst].ops[seq] = NodeOp::CallOperand;
                             }
-                            self.nodes[dst].ops[seq] = NodeOp::Call(*def_id);
                         }
                     }
+                    Operand::Move(_) | Operand::Copy(_) => {
+                        self.add_operand(func, dst); //the func is a place
+                        for arg in args.iter() {
+                            self.add_call_argument(arg, dst);
+                        }
+                        self.nodes[dst].ops[seq] = NodeOp::CallOperand;
+                    }
+                }
+                self.nodes[dst].span = terminator.source_info.span;
+                self.nodes[dst].scope = self.scope;
+                self.nodes[dst].location = self.location;
+                self.nodes[dst].seq = seq + 1;
+                self.nodes[dst].global_seq.push(self.global_seq);
+            }
+            TerminatorKind::Drop { place, .. } => {
+                // the dropped value flows into a new marker node, where the destructor may run
+                let src = self.parse_place(place);
+                let dst = self.add_marker_node(NodeOp::Drop, terminator.source_info.span);
+                self.add_node_edge(src, dst, EdgeOp::Nop);
+            }
+            TerminatorKind::SwitchInt { discr, .. } => {
+                // the value of the discriminant decides which branch to take
+                let dst = self.add_marker_node(NodeOp::SwitchDiscr, terminator.source_info.span);
+                // the operand may add the markers of its projections after this one
+                switch_discr = Some(dst);
+                self.add_operand(discr, dst);
+            }
+            TerminatorKind::Assert { cond, msg, .. } => {
+                // a runtime check which panics if the condition does not hold, e.g., the bounds and overflow checks
+                let dst = self.add_marker_node(
+                    NodeOp::Assert(format!("{:?}", msg)),
+                    terminator.source_info.span,
+                );
+                self.add_operand(cond, dst);
+            }
+            TerminatorKind::InlineAsm { operands, .. } => {
+                // the semantics of the assembly is unknown, so all the outputs are conservatively
+                // considered to depend on all the inputs
+                let mut inputs: Vec<&Operand> = vec![];
+                let mut outputs: Vec<&Place> = vec![];
+                for operand in operands.iter() {
+                    match operand {
+                        InlineAsmOperand::In { value, .. } => inputs.push(value),
+                        InlineAsmOperand::Out { place, .. } => outputs.extend(place.iter()),
+                        InlineAsmOperand::InOut {
+                            in_value,
+                            out_place,
+                            ..
+                        } => {
+                            inputs.push(in_value);
+                            outputs.extend(out_place.iter());
+                        }
+                        //consts, symbols and labels carry no dataflow
+                        _ => {}
+                    }
                 }
-                Operand::Move(_) => {
-                    self.add_operand(func, dst); //the func is a place
-                    for op in args.iter() {
-                        //rustc version related
-                        self.add_operand(&op.node, dst);
+                let span = terminator.source_info.span;
+                if outputs.is_empty() {
+                    // the inputs still flow into the assembly, e.g., a store through a pointer
+                    let dst = self.add_marker_node(NodeOp::InlineAsm, span);
+                    for input in inputs.iter() {
+                        self.add_operand(input, dst);
                     }
-                    self.nodes[dst].ops[seq] = NodeOp::CallOperand;
                 }
-                _ => {
-                    println!("{:?}", func);
-                    todo!();
+                for place in outputs {
+                    let dst = place.local;
+                    let seq = self.nodes[dst].seq;
+                    if seq == self.nodes[dst].ops.len() {
+                        self.nodes[dst].ops.push(NodeOp::Nop);
+                    }
+                    for input in inputs.iter() {
+                        self.add_operand(input, dst);
+                    }
+                    self.nodes[dst].ops[seq] = NodeOp::InlineAsm;
+                    self.nodes[dst].span = span;
+                    self.nodes[dst].scope = self.scope;
+                    self.nodes[dst].location = self.location;
+                    self.nodes[dst].seq = seq + 1;
+                    self.nodes[dst].global_seq.push(self.global_seq);
                 }
             }
-            self.nodes[dst].span = terminator.source_info.span;
-            self.nodes[dst].seq = seq + 1;
+            TerminatorKind::Yield {
+                value, resume_arg, ..
+            } => {
+                // the yielded value leaves the body, which is only seen before the state transform of coroutines,
+                // e.g., in `mir_promoted`
+                let span = terminator.source_info.span;
+                let marker = self.add_marker_node(NodeOp::Yield, span);
+                self.add_operand(value, marker);
+                // the resumed value comes from the outside, so the resume argument has no in_edges in this batch
+                let dst = self.parse_place(resume_arg);
+                let seq = self.nodes[dst].seq;
+                if seq == self.nodes[dst].ops.len() {
+                    self.nodes[dst].ops.push(NodeOp::Nop);
+                }
+                self.nodes[dst].ops[seq] = NodeOp::Yield;
+                self.nodes[dst].span = span;
+                self.nodes[dst].scope = self.scope;
+                self.nodes[dst].location = self.location;
+                self.nodes[dst].seq = seq + 1;
+                self.nodes[dst].global_seq.push(self.global_seq);
+            }
+            _ => {}
         }
+        self.global_seq += 1;
+        switch_discr
+    }
+
+    // Collect the nodes whose values are used as the discriminants of branches,
+    // i.e., the values that drive the control flow.
+    pub fn switch_discriminants(&self) -> Vec<Local> {
+        self.nodes
+            .iter()
+            .filter(|node| matches!(node.ops[0], NodeOp::SwitchDiscr))
+            .flat_map(|node| {
+                node.in_edges
+                    .iter()
+                    .map(|edge_idx| self.edges[*edge_idx].src)
+            })
+            .collect()
     }
 
     // Because a node(local) may have multiple ops, we need to decide whether to strictly collect equivalent locals or not
@@ -341,6 +864,33 @@ impl Graph {
     // For the later, if only one op meets the condition, we still take it into consideration.
     pub fn collect_equivalent_locals(&self, local: Local, strict: bool) -> HashSet<Local> {
         let mut set = HashSet::new();
+        self.collect_equivalent_locals_into(local, strict, &mut set);
+        set
+    }
+
+    // Same as `collect_equivalent_locals`, but the equivalent locals are inserted into the given set,
+    // so that the allocation can be reused when computing the classes of many locals.
+    // Nothing is done if the local is already in the set, and the traversal stops at the locals in the set.
+    pub fn collect_equivalent_locals_into(
+        &self,
+        local: Local,
+        strict: bool,
+        set: &mut HashSet<Local>,
+    ) {
+        self.collect_equivalent_locals_in_mode(local, strict, EquivalenceMode::PointsTo, set);
+    }
+
+    // Same as `collect_equivalent_locals_into`, but how references are treated is decided by the mode.
+    pub fn collect_equivalent_locals_in_mode(
+        &self,
+        local: Local,
+        strict: bool,
+        mode: EquivalenceMode,
+        set: &mut HashSet<Local>,
+    ) {
+        if set.contains(&local) {
+            return;
+        }
         let mut root = local;
         let reduce_func = if strict {
             DFSStatus::and
@@ -353,7 +903,12 @@ impl Graph {
                 .iter()
                 .map(|op| {
                     match op {
-                        NodeOp::Nop | NodeOp::Use | NodeOp::Ref => {
+                        NodeOp::Ref if mode == EquivalenceMode::ValueEqual => {
+                            // a reference is a new value, so the class starts from it
+                            root = idx;
+                            DFSStatus::Stop
+                        }
+                        NodeOp::Nop | NodeOp::Use | NodeOp::Ref | NodeOp::CopyForDeref => {
                             //Nop means an orphan node or a parameter
                             root = idx;
                             DFSStatus::Continue
@@ -364,6 +919,14 @@ impl Graph {
                 .reduce(reduce_func)
                 .unwrap()
         };
+        // Algorithm: dfs along upside to find the root node, and then dfs along downside to collect equivalent locals
+        self.dfs(
+            local,
+            Direction::Upside,
+            &mut find_root_operator,
+            &mut Self::equivalent_edge_validator,
+            true,
+        );
         let mut find_equivalent_operator = |graph: &Graph, idx: Local| -> DFSStatus {
             let node = &graph.nodes[idx];
             if set.contains(&idx) {
@@ -372,7 +935,10 @@ impl Graph {
             node.ops
                 .iter()
                 .map(|op| match op {
-                    NodeOp::Nop | NodeOp::Use | NodeOp::Ref => {
+                    NodeOp::Ref if mode == EquivalenceMode::ValueEqual && idx != root => {
+                        DFSStatus::Stop
+                    }
+                    NodeOp::Nop | NodeOp::Use | NodeOp::Ref | NodeOp::CopyForDeref => {
                         set.insert(idx);
                         DFSStatus::Continue
                     }
@@ -381,14 +947,6 @@ impl Graph {
                 .reduce(reduce_func)
                 .unwrap()
         };
-        // Algorithm: dfs along upside to find the root node, and then dfs along downside to collect equivalent locals
-        self.dfs(
-            local,
-            Direction::Upside,
-            &mut find_root_operator,
-            &mut Self::equivalent_edge_validator,
-            true,
-        );
         self.dfs(
             root,
             Direction::Downside,
@@ -396,64 +954,454 @@ impl Graph {
             &mut Self::equivalent_edge_validator,
             true,
         );
-        set
+    }
+
+    // Partition all the mir locals into equivalence classes at once by union-find,
+    // which is much faster than calling `collect_equivalent_locals` for each local.
+    // Two nodes are united if they are connected by an edge followed by `equivalent_edge_validator`
+    // and the dst is written by equivalent ops only, like the non-strict `collect_equivalent_locals`.
+    // The classes are ordered by their smallest locals.
+    pub fn equivalence_classes(&self) -> Vec<HashSet<Local>> {
+        let roots = self.union_find_roots(|edge_idx, edge| {
+            matches!(
+                Self::equivalent_edge_validator(self, edge_idx),
+                DFSStatus::Continue
+            ) && self.nodes[edge.dst].ops.iter().any(|op| {
+                matches!(
+                    op,
+                    NodeOp::Nop | NodeOp::Use | NodeOp::Ref | NodeOp::CopyForDeref
+                )
+            })
+        });
+        let mut classes: Vec<HashSet<Local>> = Vec::new();
+        let mut class_indices: HashMap<Local, usize> = HashMap::new();
+        for local in (0..self.n_locals).map(Local::from_usize) {
+            let class_idx = *class_indices.entry(roots[local]).or_insert_with(|| {
+                classes.push(HashSet::new());
+                classes.len() - 1
+            });
+            classes[class_idx].insert(local);
+        }
+        classes
+    }
+
+    // Whether the two locals may refer to the same underlying value, which is a conservative
+    // Steensgaard-style approximation: the src and the dst of every edge moving, borrowing, dereferencing
+    // or projecting a place are unified, so a reference is in the same class as its referent.
+    // The classes are recomputed for each query, so use `alias_classes` for many queries.
+    pub fn may_alias(&self, a: Local, b: Local) -> bool {
+        let roots = self.alias_classes();
+        roots[a] == roots[b]
+    }
+
+    // The representative of the alias class of each node, see `may_alias`.
+    pub fn alias_classes(&self) -> IndexVec<Local, Local> {
+        self.union_find_roots(|_, edge| {
+            matches!(
+                edge.op,
+                EdgeOp::Copy
+                    | EdgeOp::Move
+                    | EdgeOp::CopyForDeref
+                    | EdgeOp::Immut
+                    | EdgeOp::Mut
+                    | EdgeOp::Deref
+                    | EdgeOp::Field(_)
+                    | EdgeOp::Downcast(_)
+                    | EdgeOp::Index
+                    | EdgeOp::ConstIndex
+                    | EdgeOp::SubSlice
+                    | EdgeOp::SubType
+            ) && self.nodes[edge.dst].ops.iter().any(|op| {
+                matches!(
+                    op,
+                    NodeOp::Nop
+                        | NodeOp::Use
+                        | NodeOp::Ref
+                        | NodeOp::AddressOf
+                        | NodeOp::RawPtr
+                        | NodeOp::CopyForDeref
+                        | NodeOp::Cast
+                )
+            })
+        })
+    }
+
+    // Unite the src and the dst of the edges satisfying `unite`, and return the root of each node.
+    // The root is the smallest node of its class.
+    fn union_find_roots<F>(&self, unite: F) -> IndexVec<Local, Local>
+    where
+        F: Fn(EdgeIdx, &GraphEdge) -> bool,
+    {
+        fn find(parents: &mut IndexVec<Local, Local>, local: Local) -> Local {
+            let mut root = local;
+            while parents[root] != root {
+                root = parents[root];
+            }
+            // path compression
+            let mut now = local;
+            while parents[now] != root {
+                let next = parents[now];
+                parents[now] = root;
+                now = next;
+            }
+            root
+        }
+        let mut parents: IndexVec<Local, Local> = self.nodes.indices().collect();
+        for (edge_idx, edge) in self.edges.iter_enumerated() {
+            if unite(edge_idx, edge) {
+                let src_root = find(&mut parents, edge.src);
+                let dst_root = find(&mut parents, edge.dst);
+                // the smaller one is the root, so that the classes are ordered by their smallest locals
+                if src_root < dst_root {
+                    parents[dst_root] = src_root;
+                } else {
+                    parents[src_root] = dst_root;
+                }
+            }
+        }
+        self.nodes
+            .indices()
+            .map(|local| find(&mut parents, local))
+            .collect()
     }
 
     pub fn is_connected(&self, idx_1: Local, idx_2: Local) -> bool {
-        let target = idx_2;
-        let find = Cell::new(false);
+        is_reachable(self, idx_1, idx_2) || is_reachable(self, idx_2, idx_1)
+    }
+
+    // Check the connectivity from one node to many targets within a single traversal.
+    // The i-th result tells whether targets[i] is reachable from `from` in the given direction.
+    // Note that `is_connected` equals to the disjunction of the results of `Upside` and `Downside`.
+    pub fn connected_targets(
+        &self,
+        from: Local,
+        targets: &[Local],
+        direction: Direction,
+    ) -> Vec<bool> {
+        let mut visited = HashSet::new();
         let mut node_operator = |_: &Graph, idx: Local| -> DFSStatus {
-            find.set(idx == target);
-            if find.get() {
-                DFSStatus::Stop
+            if visited.insert(idx) {
+                DFSStatus::Continue
             } else {
-                // if not found, move on
+                DFSStatus::Stop
+            }
+        };
+        self.dfs(
+            from,
+            direction,
+            &mut node_operator,
+            &mut Self::always_true_edge_validator,
+            true,
+        );
+        targets
+            .iter()
+            .map(|target| visited.contains(target))
+            .collect()
+    }
+
+    // Whether there exists dataflow between each parameter and the return value
+    pub fn param_return_deps(&self) -> IndexVec<Local, bool> {
+        let _0 = self.return_local();
+        let deps = std::iter::once(_0) //the length is argc + 1, because _0 depends on _0 itself.
+            .chain(self.params())
+            .map(|_i| self.is_connected(_i, _0))
+            .collect();
+        deps
+    }
+
+    // Whether each argument of the call flows into its result, according to the `param_return_deps` of the callee,
+    // which is more precise than assuming that the result depends on all the arguments without inlining.
+    // The call is the batch of `call_local` calling the def id of `callee_graph`, and the result is empty if there is none.
+    // The arguments without a corresponding parameter, e.g., those of the `rust-call` abi, are conservatively relevant.
+    pub fn call_arg_dependencies(&self, call_local: Local, callee_graph: &Graph) -> Vec<bool> {
+        let node = &self.nodes[call_local];
+        let Some(seq) = node
+            .ops
+            .iter()
+            .position(|op| matches!(op, NodeOp::Call(def_id) if *def_id == callee_graph.def_id))
+        else {
+            return vec![];
+        };
+        let deps = callee_graph.param_return_deps();
+        let n_args = node
+            .in_edges
+            .iter()
+            .filter(|edge_idx| self.edges[**edge_idx].seq == seq)
+            .count();
+        (0..n_args)
+            .map(|i| deps.get(Local::from_usize(i + 1)).copied().unwrap_or(true))
+            .collect()
+    }
+
+    // Collect the parameters whose values flow into the return value, which would taint it if they were tainted.
+    // Unlike `param_return_deps`, the traversal follows `value_flow_edge_validator`, so a parameter only reaching
+    // the return value through a deref is excluded, e.g., `_1` in `fn f(x: &i32) -> i32 { *x }`.
+    pub fn params_tainting_return(&self) -> Vec<Local> {
+        let ret = self.return_local();
+        self.params()
+            .filter(|param| {
+                let mut visited = HashSet::new();
+                let mut node_operator = |_: &Graph, idx: Local| -> DFSStatus {
+                    if visited.insert(idx) {
+                        DFSStatus::Continue
+                    } else {
+                        DFSStatus::Stop
+                    }
+                };
+                self.dfs(
+                    *param,
+                    Direction::Downside,
+                    &mut node_operator,
+                    &mut Self::value_flow_edge_validator,
+                    true,
+                );
+                visited.contains(&ret)
+            })
+            .collect()
+    }
+
+    // Collect the locals whose values are fully derived from constants, e.g., `_1` in `_1 = Add(const 2, const 3)`.
+    // A local is const-only if all its in_edges are value edges from consts or other const-only nodes,
+    // and it is not written by a call or by taking an address. The locals on a cycle are never const-only.
+    pub fn const_only_locals(&self) -> Vec<Local> {
+        let mut const_only: IndexVec<Local, bool> = self
+            .nodes
+            .iter()
+            .map(|node| matches!(node.ops[0], NodeOp::Const(_)))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (local, node) in self.nodes.iter_enumerated() {
+                if const_only[local] || node.in_edges.is_empty() {
+                    continue;
+                }
+                let has_value_ops = node.ops.iter().all(|op| {
+                    !matches!(
+                        op,
+                        NodeOp::Err
+                            | NodeOp::Ref
+                            | NodeOp::ThreadLocalRef
+                            | NodeOp::AddressOf
+                            | NodeOp::RawPtr
+                            | NodeOp::Call(_)
+                            | NodeOp::CallOperand
+                            | NodeOp::Drop
+                            | NodeOp::SwitchDiscr
+                            | NodeOp::InlineAsm
+                            | NodeOp::Assert(_)
+                            | NodeOp::Yield
+                    )
+                });
+                let has_const_sources = node.in_edges.iter().all(|edge_idx| {
+                    let edge = &self.edges[*edge_idx];
+                    !matches!(edge.op, EdgeOp::Deref | EdgeOp::Mut | EdgeOp::Immut)
+                        && const_only[edge.src]
+                });
+                if has_value_ops && has_const_sources {
+                    const_only[local] = true;
+                    changed = true;
+                }
+            }
+        }
+        const_only
+            .iter_enumerated()
+            .filter(|(local, is_const_only)| **is_const_only && local.as_usize() < self.n_locals)
+            .map(|(local, _)| local)
+            .collect()
+    }
+
+    // Whether some value flowing into the local is a const, e.g., `_2` in `_2 = Add(copy _1, const 3)`.
+    pub fn constant_influence(&self, local: Local) -> bool {
+        dfs(self, local, Direction::Upside)
+            .into_iter()
+            .any(|idx| matches!(self.nodes[idx].ops[0], NodeOp::Const(_)))
+    }
+
+    // Whether all the values flowing into the local originate from consts, e.g., `_1` in `_1 = Add(const 2, const 3)`.
+    // Unlike `const_only_locals`, the ops on the way are not checked, so a call on consts is also influenced only by consts.
+    pub fn only_constant_influence(&self, local: Local) -> bool {
+        dfs(self, local, Direction::Upside)
+            .into_iter()
+            .filter(|idx| self.nodes[*idx].in_edges.is_empty())
+            .all(|idx| matches!(self.nodes[idx].ops[0], NodeOp::Const(_)))
+    }
+
+    // Collect all the locals flowing into the return value, including `_0` itself.
+    // The other locals do not contribute to the output, e.g., those only used for side effects.
+    pub fn return_relevant_locals(&self) -> HashSet<Local> {
+        let mut visited = HashSet::new();
+        let mut node_operator = |_: &Graph, idx: Local| -> DFSStatus {
+            if visited.insert(idx) {
                 DFSStatus::Continue
+            } else {
+                DFSStatus::Stop
             }
         };
         self.dfs(
-            idx_1,
-            Direction::Downside,
+            self.return_local(),
+            Direction::Upside,
             &mut node_operator,
             &mut Self::always_true_edge_validator,
-            false,
+            true,
         );
-        if !find.get() {
+        visited
+            .into_iter()
+            .filter(|local| local.as_usize() < self.n_locals)
+            .collect()
+    }
+
+    // Collect the references flowing into the return value, i.e., the function returns a borrow. They are the ones
+    // created by `Ref`, and the parameters of reference types or their copies, e.g., `_1` for `_0 = copy _1`.
+    // Whether they borrow from some parameter can be checked further with `is_connected`.
+    pub fn returned_references(&self) -> Vec<Local> {
+        let mut references: Vec<Local> = self
+            .return_relevant_locals()
+            .into_iter()
+            .filter(|local| {
+                let node = &self.nodes[*local];
+                let is_param = local.as_usize() >= 1 && local.as_usize() <= self.argc;
+                node.ops.iter().any(|op| matches!(op, NodeOp::Ref))
+                    || (local.as_usize() != 0
+                        && self.is_ref_local(*local)
+                        && (is_param || node.ops.iter().any(|op| matches!(op, NodeOp::Use))))
+            })
+            .collect();
+        references.sort();
+        references
+    }
+
+    // Collect all the locals whose values depend on any parameter, including the parameters themselves.
+    // Only the edges passing `value_flow_edge_validator` are followed, e.g., a value read through a deref of
+    // a parameter does not depend on it. The locals not in the set are derived from constants only (or never assigned).
+    pub fn arg_dependent_locals(&self) -> HashSet<Local> {
+        let mut visited = HashSet::new();
+        let mut node_operator = |_: &Graph, idx: Local| -> DFSStatus {
+            if visited.insert(idx) {
+                DFSStatus::Continue
+            } else {
+                DFSStatus::Stop
+            }
+        };
+        for param in self.params() {
             self.dfs(
-                idx_1,
-                Direction::Upside,
+                param,
+                Direction::Downside,
                 &mut node_operator,
-                &mut Self::always_true_edge_validator,
-                false,
+                &mut Self::value_flow_edge_validator,
+                true,
             );
         }
-        find.get()
+        visited
+            .into_iter()
+            .filter(|local| local.as_usize() < self.n_locals)
+            .collect()
     }
 
-    // Whether there exists dataflow between each parameter and the return value
-    pub fn param_return_deps(&self) -> IndexVec<Local, bool> {
-        let _0 = Local::from_usize(0);
-        let deps = (0..self.argc + 1) //the length is argc + 1, because _0 depends on _0 itself.
-            .map(|i| {
-                let _i = Local::from_usize(i);
-                self.is_connected(_i, _0)
-            })
-            .collect();
-        deps
+    // Walk the value flow from the root depth-first, calling `enter` before the nodes next to a node in the direction
+    // and `exit` after them, e.g., for the rewriting passes over the tree of values. Only the edges accepted by
+    // `value_flow_edge_validator` are followed, and the nodes next to a node are walked in the order of its edges.
+    // Each node is walked once, so the back-edges of the cycles, i.e., those to the nodes being walked, and the edges
+    // to the nodes walked already are skipped, which turns the reachable part into a spanning tree.
+    pub fn walk<F, G>(&self, root: Local, direction: Direction, enter: &mut F, exit: &mut G)
+    where
+        F: FnMut(&Graph, Local),
+        G: FnMut(&Graph, Local),
+    {
+        fn walk_from<F, G>(
+            graph: &Graph,
+            now: Local,
+            direction: Direction,
+            enter: &mut F,
+            exit: &mut G,
+            walked: &mut HashSet<Local>,
+        ) where
+            F: FnMut(&Graph, Local),
+            G: FnMut(&Graph, Local),
+        {
+            walked.insert(now);
+            enter(graph, now);
+            let node = &graph.nodes[now];
+            let upside = node
+                .in_edges
+                .iter()
+                .map(|edge_idx| (*edge_idx, graph.edges[*edge_idx].src));
+            let downside = node
+                .out_edges
+                .iter()
+                .map(|edge_idx| (*edge_idx, graph.edges[*edge_idx].dst));
+            let nexts: Vec<(EdgeIdx, Local)> = match direction {
+                Direction::Upside => upside.collect(),
+                Direction::Downside => downside.collect(),
+                Direction::Both => upside.chain(downside).collect(),
+            };
+            for (edge_idx, next) in nexts {
+                if !walked.contains(&next)
+                    && matches!(
+                        Graph::value_flow_edge_validator(graph, edge_idx),
+                        DFSStatus::Continue
+                    )
+                {
+                    walk_from(graph, next, direction, enter, exit, walked);
+                }
+            }
+            exit(graph, now);
+        }
+        walk_from(self, root, direction, enter, exit, &mut HashSet::new());
     }
 
-    // This function uses precedence traversal.
-    // The node operator and edge validator decide how far the traversal can reach.
-    // `traverse_all` decides if a branch finds the target successfully, whether the traversal will continue or not.
-    // For example, if you need to instantly stop the traversal once finding a certain node, then set `traverse_all` to false.
-    // If you want to traverse all the reachable nodes which are decided by the operator and validator, then set `traverse_all` to true.
-    pub fn dfs<F, G>(
+    // Same as `walk`, where the callback is called on a node before the nodes next to it.
+    pub fn walk_preorder<F>(&self, root: Local, direction: Direction, callback: &mut F)
+    where
+        F: FnMut(&Graph, Local),
+    {
+        self.walk(root, direction, callback, &mut |_, _| {});
+    }
+
+    // Same as `walk`, where the callback is called on a node after the nodes next to it,
+    // e.g., the users of a value are visited before it when walking downside.
+    pub fn walk_postorder<F>(&self, root: Local, direction: Direction, callback: &mut F)
+    where
+        F: FnMut(&Graph, Local),
+    {
+        self.walk(root, direction, &mut |_, _| {}, callback);
+    }
+
+    // Same as `dfs`, but the markers, e.g., the projections and the consts, are passed through transparently,
+    // i.e., the node operator only sees the locals in mir, as if the edges around a marker connected its neighbors.
+    // The edges into and out of the markers are still checked by the edge validator.
+    // Each marker is passed through at most once, so the traversal terminates as long as the operator stops at
+    // the visited locals.
+    pub fn dfs_skip_markers<F, G>(
+        &self,
+        now: Local,
+        direction: Direction,
+        node_operator: &mut F,
+        edge_validator: &mut G,
+        traverse_all: bool,
+    ) -> DFSStatus
+    where
+        F: FnMut(&Graph, Local) -> DFSStatus,
+        G: FnMut(&Graph, EdgeIdx) -> DFSStatus,
+    {
+        self.dfs_skip_markers_from(
+            now,
+            direction,
+            node_operator,
+            edge_validator,
+            traverse_all,
+            &mut HashSet::new(),
+        )
+    }
+
+    fn dfs_skip_markers_from<F, G>(
         &self,
         now: Local,
         direction: Direction,
         node_operator: &mut F,
         edge_validator: &mut G,
         traverse_all: bool,
+        visited_markers: &mut HashSet<Local>,
     ) -> DFSStatus
     where
         F: FnMut(&Graph, Local) -> DFSStatus,
@@ -464,12 +1412,13 @@ impl Graph {
                 for edge_idx in self.nodes[now].$edges.iter() {
                     let edge = &self.edges[*edge_idx];
                     if matches!(edge_validator(self, *edge_idx), DFSStatus::Continue) {
-                        let result = self.dfs(
+                        let result = self.dfs_skip_markers_from(
                             edge.$field,
                             direction,
                             node_operator,
                             edge_validator,
                             traverse_all,
+                            visited_markers,
                         );
                         if matches!(result, DFSStatus::Stop) && !traverse_all {
                             return DFSStatus::Stop;
@@ -479,24 +1428,1326 @@ impl Graph {
             };
         }
 
-        if matches!(node_operator(self, now), DFSStatus::Continue) {
-            match direction {
-                Direction::Upside => {
-                    traverse!(in_edges, src);
-                }
-                Direction::Downside => {
-                    traverse!(out_edges, dst);
-                }
-                Direction::Both => {
-                    traverse!(in_edges, src);
-                    traverse!(out_edges, dst);
-                }
-            };
-            DFSStatus::Continue
-        } else {
-            DFSStatus::Stop
+        if now.as_usize() >= self.n_locals {
+            if !visited_markers.insert(now) {
+                // passed through already, which is neither a hit nor a miss
+                return DFSStatus::Continue;
+            }
+        } else if matches!(node_operator(self, now), DFSStatus::Stop) {
+            return DFSStatus::Stop;
         }
-    }
+        match direction {
+            Direction::Upside => {
+                traverse!(in_edges, src);
+            }
+            Direction::Downside => {
+                traverse!(out_edges, dst);
+            }
+            Direction::Both => {
+                traverse!(in_edges, src);
+                traverse!(out_edges, dst);
+            }
+        };
+        DFSStatus::Continue
+    }
+
+    // This function uses precedence traversal.
+    // The node operator and edge validator decide how far the traversal can reach.
+    // `traverse_all` decides if a branch finds the target successfully, whether the traversal will continue or not.
+    // For example, if you need to instantly stop the traversal once finding a certain node, then set `traverse_all` to false.
+    // If you want to traverse all the reachable nodes which are decided by the operator and validator, then set `traverse_all` to true.
+    pub fn dfs<F, G>(
+        &self,
+        now: Local,
+        direction: Direction,
+        node_operator: &mut F,
+        edge_validator: &mut G,
+        traverse_all: bool,
+    ) -> DFSStatus
+    where
+        F: FnMut(&Graph, Local) -> DFSStatus,
+        G: FnMut(&Graph, EdgeIdx) -> DFSStatus,
+    {
+        dfs_with(
+            self,
+            now,
+            direction,
+            node_operator,
+            edge_validator,
+            traverse_all,
+        )
+    }
+
+    // Sort all the nodes topologically along the edges, i.e., the src of an edge precedes its dst.
+    // If the graph is not a DAG, the members of one cycle are returned as the error (in the edge order).
+    pub fn topo_order(&self) -> Result<Vec<Local>, Vec<Local>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            OnStack,
+            Done,
+        }
+        let mut marks: IndexVec<Local, Mark> =
+            IndexVec::from_elem_n(Mark::Unvisited, self.nodes.len());
+        let mut order = Vec::with_capacity(self.nodes.len());
+        for start in self.nodes.indices() {
+            if marks[start] != Mark::Unvisited {
+                continue;
+            }
+            // each frame records a node and the position of its next out_edge to be visited
+            let mut stack: Vec<(Local, usize)> = vec![(start, 0)];
+            marks[start] = Mark::OnStack;
+            while let Some((now, next)) = stack.last().copied() {
+                if let Some(edge_idx) = self.nodes[now].out_edges.get(next) {
+                    stack.last_mut().unwrap().1 += 1;
+                    let dst = self.edges[*edge_idx].dst;
+                    match marks[dst] {
+                        Mark::Unvisited => {
+                            marks[dst] = Mark::OnStack;
+                            stack.push((dst, 0));
+                        }
+                        Mark::OnStack => {
+                            // the frames from dst to the top of the stack form a cycle
+                            let pos = stack.iter().position(|(local, _)| *local == dst).unwrap();
+                            return Err(stack[pos..].iter().map(|(local, _)| *local).collect());
+                        }
+                        Mark::Done => {}
+                    }
+                } else {
+                    marks[now] = Mark::Done;
+                    order.push(now);
+                    stack.pop();
+                }
+            }
+        }
+        order.reverse();
+        Ok(order)
+    }
+
+    // A debug helper for the analyses assuming the graph is a DAG.
+    // Returns the members of one cycle if there is any.
+    pub fn assert_acyclic(&self) -> Result<(), Vec<Local>> {
+        self.topo_order().map(|_| ())
+    }
+
+    // Find the longest chain of value-flow edges (see `value_flow_edge_validator`), which approximates the depth
+    // of the computation. The chain is returned from its source to its sink, and a single node if there is no edge.
+    // Same as `topo_order`, the members of one cycle are returned as the error.
+    pub fn longest_path(&self) -> Result<Vec<Local>, Vec<Local>> {
+        let order = self.topo_order()?;
+        // the length of the longest chain ending at each node, and the previous node on it
+        let mut depth: IndexVec<Local, usize> = IndexVec::from_elem_n(0, self.nodes.len());
+        let mut prev: IndexVec<Local, Option<Local>> =
+            IndexVec::from_elem_n(None, self.nodes.len());
+        for &now in order.iter() {
+            for edge_idx in self.nodes[now].out_edges.iter() {
+                if matches!(
+                    Self::value_flow_edge_validator(self, *edge_idx),
+                    DFSStatus::Stop
+                ) {
+                    continue;
+                }
+                let dst = self.edges[*edge_idx].dst;
+                if depth[now] + 1 > depth[dst] {
+                    depth[dst] = depth[now] + 1;
+                    prev[dst] = Some(now);
+                }
+            }
+        }
+        let Some(mut now) = order.iter().copied().max_by_key(|local| depth[*local]) else {
+            return Ok(vec![]);
+        };
+        let mut path = vec![now];
+        while let Some(src) = prev[now] {
+            path.push(src);
+            now = src;
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    // Count the distinct paths of value-flow edges (see `value_flow_edge_validator`) from one node to another,
+    // where the parallel edges, e.g., those of `_1 + _1`, lead to different paths.
+    // A large count signals heavily reconverging dataflow.
+    // The count saturates at `usize::MAX`. Same as `topo_order`, the members of one cycle are returned as the error.
+    pub fn path_count(&self, from: Local, to: Local) -> Result<usize, Vec<Local>> {
+        let order = self.topo_order()?;
+        let mut counts: IndexVec<Local, usize> = IndexVec::from_elem_n(0, self.nodes.len());
+        counts[from] = 1;
+        // the nodes before `from` in the order are not reachable from it
+        for &now in order.iter().skip_while(|local| **local != from) {
+            if now == to {
+                break;
+            }
+            for edge_idx in self.nodes[now].out_edges.iter() {
+                if matches!(
+                    Self::value_flow_edge_validator(self, *edge_idx),
+                    DFSStatus::Stop
+                ) {
+                    continue;
+                }
+                let dst = self.edges[*edge_idx].dst;
+                counts[dst] = counts[dst].saturating_add(counts[now]);
+            }
+        }
+        Ok(counts[to])
+    }
+
+    // Find the cheapest path of edges from one node to another by Dijkstra, where the cost of each edge is given,
+    // e.g., `Graph::projection_cost`. The path is returned from `from` to `to`, and None if `to` is not reachable.
+    // Among the paths of the same cost, the one found first is returned.
+    pub fn shortest_path_by<F>(&self, from: Local, to: Local, cost: F) -> Option<Vec<Local>>
+    where
+        F: Fn(&GraphEdge) -> usize,
+    {
+        let mut dist: IndexVec<Local, Option<usize>> =
+            IndexVec::from_elem_n(None, self.nodes.len());
+        let mut prev: IndexVec<Local, Option<Local>> =
+            IndexVec::from_elem_n(None, self.nodes.len());
+        let mut heap = BinaryHeap::new();
+        dist[from] = Some(0);
+        heap.push(Reverse((0, from)));
+        while let Some(Reverse((d, now))) = heap.pop() {
+            if now == to {
+                break;
+            }
+            if dist[now].is_some_and(|best| d > best) {
+                continue; //a stale entry
+            }
+            for edge_idx in self.nodes[now].out_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                let d = d.saturating_add(cost(edge));
+                if dist[edge.dst].map_or(true, |best| d < best) {
+                    dist[edge.dst] = Some(d);
+                    prev[edge.dst] = Some(now);
+                    heap.push(Reverse((d, edge.dst)));
+                }
+            }
+        }
+        dist[to]?;
+        let mut path = vec![to];
+        let mut now = to;
+        while now != from {
+            now = prev[now].unwrap();
+            path.push(now);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // The path with the fewest projection hops, i.e., the most direct value dependency, see `projection_cost`.
+    pub fn most_direct_path(&self, from: Local, to: Local) -> Option<Vec<Local>> {
+        self.shortest_path_by(from, to, Self::projection_cost)
+    }
+
+    // The value edges cost nothing, while each projection (including a deref) costs 1.
+    pub fn projection_cost(edge: &GraphEdge) -> usize {
+        match edge.op {
+            EdgeOp::Deref
+            | EdgeOp::Field(_)
+            | EdgeOp::Downcast(_)
+            | EdgeOp::Index
+            | EdgeOp::ConstIndex
+            | EdgeOp::SubSlice
+            | EdgeOp::SubType
+            | EdgeOp::UnknownProjection(_) => 1,
+            _ => 0,
+        }
+    }
+
+    // Collect the locals (excluding _0 and markers) which are assigned but never read, i.e., dead assignments.
+    // Being dropped does not count as a read, while accessing any projection of a local does.
+    // The destinations of calls are skipped, since a call has to write its result somewhere even if it is `()`.
+    pub fn dead_locals(&self) -> Vec<Local> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(local, node)| {
+                local.as_usize() != 0
+                    && local.as_usize() < self.n_locals
+                    && !node.in_edges.is_empty()
+                    && !node
+                        .ops
+                        .iter()
+                        .any(|op| matches!(op, NodeOp::Call(_) | NodeOp::CallOperand))
+                    && node.out_edges.iter().all(|edge_idx| {
+                        matches!(self.nodes[self.edges[*edge_idx].dst].ops[0], NodeOp::Drop)
+                    })
+            })
+            .map(|(local, _)| local)
+            .collect()
+    }
+
+    // Collect the write batches that may define the value of the local, as (node, seq) pairs
+    // ordered by when they are written, where the node is the local itself or its projection, e.g., `_1.0 = ..`.
+    // The writes through derefs are not included since they define the pointee instead.
+    // The graph does not keep the control flow, so all the writes are considered to reach the use.
+    pub fn reaching_defs(&self, local: Local) -> Vec<(Local, u32)> {
+        let mut defs = vec![];
+        let mut stack = vec![local];
+        while let Some(now) = stack.pop() {
+            let node = &self.nodes[now];
+            for seq in 0..node.seq {
+                let global_seq = node.global_seq.get(seq).copied().unwrap_or(usize::MAX);
+                defs.push((global_seq, now, seq));
+            }
+            for edge_idx in node.out_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                // the first in_edge of a projection marker is from its base, e.g., not from the index of `_1[_2]`
+                if edge.dst.as_usize() >= self.n_locals
+                    && self.nodes[edge.dst].in_edges.first() == Some(edge_idx)
+                    && matches!(
+                        edge.op,
+                        EdgeOp::Field(_)
+                            | EdgeOp::Downcast(_)
+                            | EdgeOp::Index
+                            | EdgeOp::ConstIndex
+                            | EdgeOp::SubSlice
+                            | EdgeOp::SubType
+                            | EdgeOp::UnknownProjection(_)
+                    )
+                {
+                    stack.push(edge.dst);
+                }
+            }
+        }
+        defs.sort_unstable();
+        defs.into_iter()
+            .map(|(_, node, seq)| (node, seq as u32))
+            .collect()
+    }
+
+    // Find the parameter which is returned as it is, e.g., `_1` in `fn id<T>(x: T) -> T { x }`.
+    // The return value and the locals in between must be assigned exactly once by a plain `Copy` or `Move`,
+    // so no projection, reference, or any other op intervenes.
+    pub fn returns_param_directly(&self) -> Option<Local> {
+        let mut local = self.return_local();
+        // each step visits a different local unless there is a cycle
+        for _ in 0..self.n_locals {
+            let node = &self.nodes[local];
+            if node.ops.len() != 1
+                || !matches!(node.ops[0], NodeOp::Use)
+                || node.in_edges.len() != 1
+            {
+                return None;
+            }
+            let edge = &self.edges[node.in_edges[0]];
+            if !matches!(edge.op, EdgeOp::Copy | EdgeOp::Move)
+                || edge.src.as_usize() >= self.n_locals
+            {
+                return None;
+            }
+            local = edge.src;
+            if self.params().any(|param| param == local) {
+                // the parameter must not be reassigned either
+                return self.nodes[local].in_edges.is_empty().then_some(local);
+            }
+        }
+        None
+    }
+
+    // Convert into a petgraph graph, so that its algorithms can be used directly, e.g., `petgraph::algo::is_cyclic_directed`.
+    // The weight of a node is its first op, and the mapping from the locals to the petgraph indices is returned too.
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> (DiGraph<NodeOp, EdgeOp>, HashMap<Local, NodeIndex>) {
+        let mut graph = DiGraph::with_capacity(self.nodes.len(), self.edges.len());
+        let mut indices = HashMap::new();
+        for (local, node) in self.nodes.iter_enumerated() {
+            indices.insert(local, graph.add_node(node.ops[0].clone()));
+        }
+        for edge in self.edges.iter() {
+            graph.add_edge(indices[&edge.src], indices[&edge.dst], edge.op.clone());
+        }
+        (graph, indices)
+    }
+
+    // Collect the mir locals where the values originate, i.e., no value flows into them except the consts,
+    // e.g., the parameters and the locals initialized by consts only. The markers are excluded.
+    pub fn roots(&self) -> Vec<Local> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(local, node)| {
+                local.as_usize() < self.n_locals
+                    && node.in_edges.iter().all(|edge_idx| {
+                        matches!(
+                            self.nodes[self.edges[*edge_idx].src].ops[0],
+                            NodeOp::Const(_)
+                        )
+                    })
+            })
+            .map(|(local, _)| local)
+            .collect()
+    }
+
+    // Collect the mir locals where the values end, i.e., they flow into no other node except being dropped
+    // or checked by branches and assertions, e.g., the return value. The markers are excluded.
+    pub fn sinks(&self) -> Vec<Local> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(local, node)| {
+                local.as_usize() < self.n_locals
+                    && node.out_edges.iter().all(|edge_idx| {
+                        matches!(
+                            self.nodes[self.edges[*edge_idx].dst].ops[0],
+                            NodeOp::Drop | NodeOp::SwitchDiscr | NodeOp::Assert(_)
+                        )
+                    })
+            })
+            .map(|(local, _)| local)
+            .collect()
+    }
+
+    // Propagate taint downside from the sources with a decaying confidence score.
+    // The score of each source is 1.0, and passing an edge multiplies the score by `edge_weight(op)`,
+    // which is clamped into [0, 1] so that the scores never grow along a cycle.
+    // A node keeps the highest score among all the paths reaching it,
+    // and only the nodes whose score is no less than `threshold` are returned and propagated further.
+    pub fn propagate<F>(
+        &self,
+        sources: &[Local],
+        edge_weight: F,
+        threshold: f32,
+    ) -> HashMap<Local, f32>
+    where
+        F: Fn(&EdgeOp) -> f32,
+    {
+        let mut scores: HashMap<Local, f32> = HashMap::new();
+        let mut worklist: Vec<Local> = Vec::new();
+        for &source in sources {
+            if 1.0 >= threshold {
+                scores.insert(source, 1.0);
+                worklist.push(source);
+            }
+        }
+        while let Some(now) = worklist.pop() {
+            let score = scores[&now];
+            for edge_idx in self.nodes[now].out_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                let next_score = score * edge_weight(&edge.op).clamp(0.0, 1.0);
+                if next_score < threshold {
+                    continue;
+                }
+                let improved = match scores.get(&edge.dst) {
+                    Some(old_score) => next_score > *old_score,
+                    None => true,
+                };
+                if improved {
+                    scores.insert(edge.dst, next_score);
+                    worklist.push(edge.dst);
+                }
+            }
+        }
+        scores
+    }
+
+    // Find the (source, sink) pairs such that the value of the source flows downside into the sink
+    // without passing any sanitizer, where the nodes are classified by their ops, e.g., a call to an escaping function.
+    // A node is a source/sink/sanitizer if any of its ops satisfies the predicate.
+    // A sanitizer stops the propagation and is never reported as a sink, while a source always propagates.
+    // The pairs are sorted.
+    pub fn flows_sanitized<S, K, Z>(
+        &self,
+        is_source: S,
+        is_sink: K,
+        is_sanitizer: Z,
+    ) -> Vec<(Local, Local)>
+    where
+        S: Fn(&NodeOp) -> bool,
+        K: Fn(&NodeOp) -> bool,
+        Z: Fn(&NodeOp) -> bool,
+    {
+        let mut flows = vec![];
+        for (source, node) in self.nodes.iter_enumerated() {
+            if !node.ops.iter().any(&is_source) {
+                continue;
+            }
+            let mut visited = HashSet::from([source]);
+            let mut worklist = vec![source];
+            while let Some(now) = worklist.pop() {
+                for edge_idx in self.nodes[now].out_edges.iter() {
+                    let dst = self.edges[*edge_idx].dst;
+                    if !visited.insert(dst) {
+                        continue;
+                    }
+                    let dst_ops = &self.nodes[dst].ops;
+                    if dst_ops.iter().any(&is_sanitizer) {
+                        continue;
+                    }
+                    if dst_ops.iter().any(&is_sink) {
+                        flows.push((source, dst));
+                    }
+                    worklist.push(dst);
+                }
+            }
+        }
+        flows.sort_unstable();
+        flows
+    }
+
+    // Collect the locals that are merely a renaming of another one, e.g., `_2 = copy _1`.
+    // A local is reported only if it is assigned once by `Use` and its only in-edge is a copy or a move.
+    // The result is a list of (local, its single source) pairs.
+    pub fn identity_locals(&self) -> Vec<(Local, Local)> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(local, _)| local.as_usize() < self.n_locals)
+            .filter_map(|(local, node)| {
+                if node.ops.len() != 1
+                    || !matches!(node.ops[0], NodeOp::Use)
+                    || node.in_edges.len() != 1
+                {
+                    return None;
+                }
+                let edge = &self.edges[node.in_edges[0]];
+                match edge.op {
+                    EdgeOp::Copy | EdgeOp::Move => Some((local, edge.src)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    // Iterate over the edges connected to the node in the given direction,
+    // yielding each edge together with the node at the other end of it.
+    // For `Direction::Both`, the in_edges are yielded before the out_edges.
+    pub fn neighbors(
+        &self,
+        local: Local,
+        direction: Direction,
+    ) -> impl Iterator<Item = (EdgeIdx, Local)> + '_ {
+        let node = &self.nodes[local];
+        let (upside, downside) = match direction {
+            Direction::Upside => (true, false),
+            Direction::Downside => (false, true),
+            Direction::Both => (true, true),
+        };
+        let in_neighbors = node
+            .in_edges
+            .iter()
+            .filter(move |_| upside)
+            .map(move |edge_idx| (*edge_idx, self.edges[*edge_idx].src));
+        let out_neighbors = node
+            .out_edges
+            .iter()
+            .filter(move |_| downside)
+            .map(move |edge_idx| (*edge_idx, self.edges[*edge_idx].dst));
+        in_neighbors.chain(out_neighbors)
+    }
+
+    // Collect the distinct functions called in the graph, ordered by the locals receiving their results.
+    // The calls through function pointers or closures in locals (`CallOperand`) are not resolved.
+    pub fn direct_callees(&self) -> Vec<DefId> {
+        let mut visited = HashSet::new();
+        self.nodes
+            .iter()
+            .flat_map(|node| node.ops.iter())
+            .filter_map(|op| match op {
+                NodeOp::Call(def_id) => Some(*def_id),
+                _ => None,
+            })
+            .filter(|def_id| visited.insert(*def_id))
+            .collect()
+    }
+
+    // Group the mir locals by their spans, so that the temporaries of one source variable are reported together.
+    // The markers and the locals without spans are skipped.
+    pub fn group_by_span(&self) -> BTreeMap<String, Vec<Local>> {
+        let mut groups: BTreeMap<String, Vec<Local>> = BTreeMap::new();
+        for (local, node) in self.nodes.iter_enumerated() {
+            if local.as_usize() >= self.n_locals || node.span == DUMMY_SP {
+                continue;
+            }
+            groups
+                .entry(span_to_string(node.span))
+                .or_default()
+                .push(local);
+        }
+        groups
+    }
+
+    // Find the mir local whose span contains the position most tightly, e.g., for the hover of editors.
+    // The markers and the locals without spans are skipped, and the smaller local wins a tie.
+    pub fn node_at(&self, pos: BytePos) -> Option<Local> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(local, node)| {
+                local.as_usize() < self.n_locals
+                    && !node.span.is_dummy()
+                    && node.span.lo() <= pos
+                    && pos < node.span.hi()
+            })
+            .min_by_key(|(local, node)| (node.span.hi() - node.span.lo(), *local))
+            .map(|(local, _)| local)
+    }
+
+    // A cheap screen for impure functions: any call, inline assembly, raw pointer or mutable borrow may cause side effects.
+    // Note that `Rvalue::RawPtr` is what `AddressOf` is called in the recent rustc versions.
+    pub fn has_side_effect_ops(&self) -> bool {
+        self.nodes.iter().any(|node| {
+            node.ops.iter().any(|op| {
+                matches!(
+                    op,
+                    NodeOp::Call(_)
+                        | NodeOp::CallOperand
+                        | NodeOp::InlineAsm
+                        | NodeOp::AddressOf
+                        | NodeOp::RawPtr
+                )
+            })
+        }) || self.edges.iter().any(|edge| matches!(edge.op, EdgeOp::Mut))
+    }
+
+    // Collect the boxes created by `ShallowInitBox`, i.e., the heap allocation sites, together with their spans.
+    // Note that `Box::new` is a call in the caller's mir unless it is inlined.
+    pub fn allocation_sites(&self) -> Vec<(Local, Span)> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(_, node)| {
+                node.ops
+                    .iter()
+                    .any(|op| matches!(op, NodeOp::ShallowInitBox))
+            })
+            .map(|(local, node)| (local, node.span))
+            .collect()
+    }
+
+    // Get the arguments fed to the call writing the local, in the order of the arguments.
+    // For `CallOperand`, the first one is the callee. If the local is written by several calls, the last one is used.
+    // The consts are returned as their const nodes, and an empty list is returned if the local is not written by a call.
+    pub fn call_arguments(&self, call_local: Local) -> Vec<Local> {
+        let node = &self.nodes[call_local];
+        let Some(seq) = node
+            .ops
+            .iter()
+            .rposition(|op| matches!(op, NodeOp::Call(_) | NodeOp::CallOperand))
+        else {
+            return vec![];
+        };
+        node.in_edges
+            .iter()
+            .map(|edge_idx| &self.edges[*edge_idx])
+            .filter(|edge| edge.seq == seq)
+            .map(|edge| edge.src)
+            .collect()
+    }
+
+    // Collect the (len, collection) pairs of `Rvalue::Len`, i.e., the locals holding the length of which collection.
+    // Note that `Rvalue::Len` is only used for arrays and slices, e.g., the bounds checks of indexing,
+    // while `Vec::len` remains a call.
+    pub fn length_queries(&self) -> Vec<(Local, Local)> {
+        let mut queries = vec![];
+        for (local, node) in self.nodes.iter_enumerated() {
+            for (seq, op) in node.ops.iter().enumerate() {
+                if !matches!(op, NodeOp::Len) {
+                    continue;
+                }
+                // a len has exactly one operand
+                if let Some(edge) = node
+                    .in_edges
+                    .iter()
+                    .map(|edge_idx| &self.edges[*edge_idx])
+                    .find(|edge| edge.seq == seq)
+                {
+                    queries.push((local, edge.src));
+                }
+            }
+        }
+        queries
+    }
+
+    // Count the `Deref` edges separating the local from its root, e.g., 2 for `_2` in `_2 = copy (*(*_1))`.
+    // The roots are the nodes without in_edges (except the consts), and the path with the fewest derefs is taken.
+    // 0 is returned if no root is reachable.
+    pub fn deref_depth(&self, local: Local) -> usize {
+        // 0-1 BFS, where only the deref edges have weight 1
+        let mut depths: HashMap<Local, usize> = HashMap::new();
+        let mut deque: VecDeque<(Local, usize)> = VecDeque::new();
+        deque.push_back((local, 0));
+        while let Some((now, depth)) = deque.pop_front() {
+            if depths.contains_key(&now) {
+                continue;
+            }
+            depths.insert(now, depth);
+            let node = &self.nodes[now];
+            if node.in_edges.is_empty() {
+                return depth;
+            }
+            for edge_idx in node.in_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                if matches!(self.nodes[edge.src].ops[0], NodeOp::Const(_))
+                    || depths.contains_key(&edge.src)
+                {
+                    continue;
+                }
+                if matches!(edge.op, EdgeOp::Deref) {
+                    deque.push_back((edge.src, depth + 1));
+                } else {
+                    deque.push_front((edge.src, depth));
+                }
+            }
+        }
+        0
+    }
+
+    // Collect the locals that are moved out more than once, which is suspicious in unsafe code
+    // since the later moves may use a value that has already been moved away.
+    pub fn multiple_moves(&self) -> Vec<Local> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(_, node)| {
+                node.out_edges
+                    .iter()
+                    .filter(|edge_idx| matches!(self.edges[**edge_idx].op, EdgeOp::Move))
+                    .count()
+                    > 1
+            })
+            .map(|(local, _)| local)
+            .collect()
+    }
+
+    // Collect the runtime checks, i.e., the checked conditions together with what they guard.
+    pub fn assertions(&self) -> Vec<(Local, &str)> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match &node.ops[0] {
+                NodeOp::Assert(msg) => node
+                    .in_edges
+                    .first()
+                    .map(|edge_idx| (self.edges[*edge_idx].src, msg.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Group the locals computed by the same operation on the same sources, i.e., the candidates for the common
+    // subexpression elimination, e.g., `_3` and `_4` in `_3 = Add(copy _1, copy _2); _4 = Add(copy _2, copy _1)`.
+    // The sources of commutative operations are unordered, the consts are compared by value, and the projections
+    // are compared by their bases and ops, and the temporaries copied once are replaced by their sources.
+    // As the graph is not in ssa form, the operations on a source being reassigned are skipped.
+    // Only the binary ops, `Len`, and `Discriminant` are considered, since the other ops, e.g., `Cast` and
+    // `UnaryOp`, do not record enough to tell whether they are the same. The groups are sorted.
+    pub fn common_subexpressions(&self) -> Vec<Vec<Local>> {
+        // describe the value of a source, or None if it may change
+        fn describe(graph: &Graph, src: Local) -> Option<String> {
+            let node = &graph.nodes[src];
+            if let NodeOp::Const(idx) = node.ops[0] {
+                return Some(format!("const {}", graph.const_value(idx)));
+            }
+            if src.as_usize() < graph.n_locals {
+                if node.global_seq.len() > 1 {
+                    return None;
+                }
+                // see through the temporaries copied once, e.g., `_6` in `_6 = copy _1`
+                if let ([NodeOp::Use], [edge_idx]) = (node.ops.as_slice(), node.in_edges.as_slice())
+                {
+                    let edge = &graph.edges[*edge_idx];
+                    if matches!(edge.op, EdgeOp::Copy | EdgeOp::Move) {
+                        return describe(graph, edge.src);
+                    }
+                }
+                return Some(format!("{:?}", src));
+            }
+            // a projection, whose first in_edge is from its base
+            let edge = &graph.edges[*node.in_edges.first()?];
+            let n_bases = if matches!(edge.op, EdgeOp::Index) {
+                2
+            } else {
+                1
+            };
+            if node.in_edges.len() > n_bases {
+                return None; //written
+            }
+            let base = describe(graph, edge.src)?;
+            match edge.op {
+                EdgeOp::Index => Some(format!(
+                    "{}[{}]",
+                    base,
+                    describe(graph, graph.edges[*node.in_edges.get(1)?].src)?
+                )),
+                _ => Some(format!("{}.{:?}", base, edge.op)),
+            }
+        }
+        let mut groups: HashMap<(String, Vec<String>), Vec<Local>> = HashMap::new();
+        for (local, node) in self.nodes.iter_enumerated() {
+            if local.as_usize() >= self.n_locals {
+                continue;
+            }
+            for (seq, op) in node.ops.iter().enumerate() {
+                let is_commutative = match op {
+                    NodeOp::BinaryOp(bin_op) | NodeOp::CheckedBinaryOp(bin_op) => matches!(
+                        bin_op,
+                        BinOp::Add
+                            | BinOp::AddUnchecked
+                            | BinOp::AddWithOverflow
+                            | BinOp::Mul
+                            | BinOp::MulUnchecked
+                            | BinOp::MulWithOverflow
+                            | BinOp::BitAnd
+                            | BinOp::BitOr
+                            | BinOp::BitXor
+                            | BinOp::Eq
+                            | BinOp::Ne
+                    ),
+                    NodeOp::Len | NodeOp::Discriminant => false,
+                    _ => continue,
+                };
+                let Some(mut sources) = node
+                    .in_edges
+                    .iter()
+                    .map(|edge_idx| &self.edges[*edge_idx])
+                    .filter(|edge| edge.seq == seq)
+                    .map(|edge| describe(self, edge.src))
+                    .collect::<Option<Vec<String>>>()
+                else {
+                    continue;
+                };
+                if is_commutative {
+                    sources.sort_unstable();
+                }
+                let group = groups.entry((format!("{:?}", op), sources)).or_default();
+                if !group.contains(&local) {
+                    group.push(local);
+                }
+            }
+        }
+        let mut groups: Vec<Vec<Local>> = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect();
+        groups.sort_unstable();
+        groups
+    }
+
+    // Find the chains of additions, subtractions and multiplications feeding each other, e.g., `a * b + c`,
+    // which are the candidate points where overflows accumulate.
+    // The values may pass through copies, casts and the fields of the checked results between two operations.
+    // Only the longest chain from each operation without arithmetic predecessors is returned (with at least 2 operations),
+    // and the cycles, e.g., the accumulations in loops, are cut.
+    pub fn arithmetic_chains(&self) -> Vec<Vec<Local>> {
+        fn is_arithmetic(node: &GraphNode) -> bool {
+            node.ops.iter().any(|op| match op {
+                NodeOp::BinaryOp(bin_op) | NodeOp::CheckedBinaryOp(bin_op) => matches!(
+                    bin_op,
+                    BinOp::Add
+                        | BinOp::AddUnchecked
+                        | BinOp::AddWithOverflow
+                        | BinOp::Sub
+                        | BinOp::SubUnchecked
+                        | BinOp::SubWithOverflow
+                        | BinOp::Mul
+                        | BinOp::MulUnchecked
+                        | BinOp::MulWithOverflow
+                ),
+                _ => false,
+            })
+        }
+        // the longest chain starting from the node, memoized
+        fn longest_chain(
+            now: Local,
+            successors: &HashMap<Local, Vec<Local>>,
+            on_path: &mut HashSet<Local>,
+            memo: &mut HashMap<Local, Vec<Local>>,
+        ) -> Vec<Local> {
+            if let Some(chain) = memo.get(&now) {
+                return chain.clone();
+            }
+            on_path.insert(now);
+            let mut longest = vec![];
+            for next in successors.get(&now).into_iter().flatten() {
+                if on_path.contains(next) {
+                    continue;
+                }
+                let chain = longest_chain(*next, successors, on_path, memo);
+                if chain.len() > longest.len() {
+                    longest = chain;
+                }
+            }
+            on_path.remove(&now);
+            longest.insert(0, now);
+            memo.insert(now, longest.clone());
+            longest
+        }
+        // the arithmetic nodes reached from each arithmetic node without passing another one
+        let mut successors: HashMap<Local, Vec<Local>> = HashMap::new();
+        for (local, node) in self.nodes.iter_enumerated() {
+            if !is_arithmetic(node) {
+                continue;
+            }
+            let mut visited = HashSet::from([local]);
+            let mut stack = vec![local];
+            while let Some(now) = stack.pop() {
+                for edge_idx in self.nodes[now].out_edges.iter() {
+                    let edge = &self.edges[*edge_idx];
+                    if !matches!(
+                        edge.op,
+                        EdgeOp::Copy | EdgeOp::Move | EdgeOp::CopyForDeref | EdgeOp::Field(_)
+                    ) || !visited.insert(edge.dst)
+                    {
+                        continue;
+                    }
+                    let dst_node = &self.nodes[edge.dst];
+                    if is_arithmetic(dst_node) {
+                        successors.entry(local).or_default().push(edge.dst);
+                    } else if dst_node.ops.iter().all(|op| {
+                        matches!(
+                            op,
+                            NodeOp::Nop | NodeOp::Use | NodeOp::CopyForDeref | NodeOp::Cast
+                        )
+                    }) {
+                        stack.push(edge.dst);
+                    }
+                }
+            }
+        }
+        let has_predecessors: HashSet<Local> = successors.values().flatten().copied().collect();
+        let mut starts: Vec<Local> = successors
+            .keys()
+            .filter(|local| !has_predecessors.contains(local))
+            .copied()
+            .collect();
+        starts.sort_unstable();
+        let mut memo = HashMap::new();
+        starts
+            .into_iter()
+            .map(|start| longest_chain(start, &successors, &mut HashSet::new(), &mut memo))
+            .filter(|chain| chain.len() >= 2)
+            .collect()
+    }
+
+    // Find the locals which are read again after being moved out without being reassigned in between,
+    // as (local, the global seq of the read) pairs, which is a smell in unsafe code, e.g., a `ptr::read` duplicate.
+    // The statements and terminators are ordered by their global seqs, i.e., the order of basic blocks,
+    // so this is only an approximation without the control flow. Being dropped does not count as a read.
+    pub fn use_after_move(&self) -> Vec<(Local, u32)> {
+        let mut found = vec![];
+        for (local, node) in self.nodes.iter_enumerated() {
+            if local.as_usize() >= self.n_locals {
+                continue;
+            }
+            let mut moves = vec![];
+            let mut uses = vec![];
+            for edge_idx in node.out_edges.iter() {
+                let edge = &self.edges[*edge_idx];
+                if matches!(self.nodes[edge.dst].ops[0], NodeOp::Drop) {
+                    continue;
+                }
+                let Some(time) = self.use_time(*edge_idx) else {
+                    continue;
+                };
+                if matches!(edge.op, EdgeOp::Move) {
+                    moves.push(time);
+                }
+                uses.push(time);
+            }
+            let Some(&first_move) = moves.iter().min() else {
+                continue;
+            };
+            let mut uses: Vec<usize> = uses.into_iter().filter(|&t| t > first_move).collect();
+            uses.sort_unstable();
+            for use_time in uses {
+                // the latest move before the use, after which the local must be reassigned
+                let last_move = moves.iter().filter(|&&t| t < use_time).max().unwrap();
+                let is_reassigned = node
+                    .global_seq
+                    .iter()
+                    .any(|&t| *last_move <= t && t < use_time);
+                if !is_reassigned {
+                    found.push((local, use_time as u32));
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    // When the value of the edge is used: the global seq of the batch of its dst,
+    // or the earliest use of the dst if it is a projection read only.
+    fn use_time(&self, edge_idx: EdgeIdx) -> Option<usize> {
+        let edge = &self.edges[edge_idx];
+        let dst = &self.nodes[edge.dst];
+        if let Some(&time) = dst.global_seq.get(edge.seq) {
+            return Some(time);
+        }
+        if edge.dst.as_usize() < self.n_locals {
+            return None;
+        }
+        dst.out_edges
+            .iter()
+            .filter_map(|edge_idx| self.use_time(*edge_idx))
+            .min()
+    }
+
+    // Count the ops of all the nodes by their variant names, where a node written several times counts once per batch.
+    pub fn op_histogram(&self) -> HashMap<&'static str, usize> {
+        let mut histogram = HashMap::new();
+        for node in self.nodes.iter() {
+            for op in node.ops.iter() {
+                *histogram.entry(op.name()).or_insert(0) += 1;
+            }
+        }
+        histogram
+    }
+
+    // Count the operands by how they are passed, which is a quick signal for the copy-heavy code.
+    pub fn operand_stats(&self) -> OperandStats {
+        let mut stats = OperandStats::default();
+        for edge in self.edges.iter() {
+            match edge.op {
+                EdgeOp::Move => stats.moves += 1,
+                EdgeOp::Copy => stats.copies += 1,
+                EdgeOp::Const => stats.consts += 1,
+                _ => {}
+            }
+        }
+        stats
+    }
+
+    // A structural fingerprint for caching, which ignores the spans and the order in which the edges are added.
+    // The consts are hashed by value rather than by their interned idx.
+    // Note that the def ids are hashed by index, so fingerprints are only comparable within one compilation session.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.argc.hash(&mut hasher);
+        self.nodes.len().hash(&mut hasher);
+        for node in self.nodes.iter() {
+            node.ops.len().hash(&mut hasher);
+            for op in node.ops.iter() {
+                match op {
+                    NodeOp::Const(idx) => {
+                        std::mem::discriminant(op).hash(&mut hasher);
+                        self.const_value(*idx).hash(&mut hasher);
+                    }
+                    _ => op.hash(&mut hasher),
+                }
+            }
+        }
+        let mut edge_hashes: Vec<u64> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let mut edge_hasher = DefaultHasher::new();
+                (edge.src, edge.dst, &edge.op).hash(&mut edge_hasher);
+                edge_hasher.finish()
+            })
+            .collect();
+        edge_hashes.sort_unstable();
+        edge_hashes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Whether the two graphs are the same up to the renaming of the nodes, e.g., for copy-pasted functions.
+    // The return value and the parameters keep their indices, the other nodes are matched by backtracking on
+    // their ops, and the edges between the matched nodes must have the same ops and seqs. The consts are
+    // compared by value and the spans are ignored. The search is exponential in the worst case, so the graphs
+    // with more than `MAX_ISOMORPHISM_NODES` nodes, or taking more than `MAX_ISOMORPHISM_STEPS` steps, are
+    // conservatively reported as different.
+    pub fn is_isomorphic_to(&self, other: &Graph) -> bool {
+        const MAX_ISOMORPHISM_NODES: usize = 64;
+        const MAX_ISOMORPHISM_STEPS: usize = 100_000;
+        if self.nodes.len() > MAX_ISOMORPHISM_NODES
+            || self.nodes.len() != other.nodes.len()
+            || self.edges.len() != other.edges.len()
+            || self.argc != other.argc
+            || self.op_histogram() != other.op_histogram()
+        {
+            return false;
+        }
+        type NodeLabels = IndexVec<Local, (Vec<String>, usize, usize)>; //the ops, the in degree, the out degree
+        type EdgesBetween<'a> = HashMap<(Local, Local), Vec<(&'a EdgeOp, usize)>>; //the sorted (op, seq) pairs
+        struct Matcher<'a> {
+            labels: [NodeLabels; 2],
+            edges: [EdgesBetween<'a>; 2],
+            mapping: IndexVec<Local, Option<Local>>,
+            is_used: IndexVec<Local, bool>,
+            steps: usize,
+        }
+        impl Matcher<'_> {
+            fn edges_between(&self, side: usize, src: Local, dst: Local) -> &[(&EdgeOp, usize)] {
+                self.edges[side].get(&(src, dst)).map_or(&[], |edges| edges)
+            }
+
+            fn is_feasible(&self, u: Local, v: Local) -> bool {
+                if self.is_used[v] || self.labels[0][u] != self.labels[1][v] {
+                    return false;
+                }
+                if self.edges_between(0, u, u) != self.edges_between(1, v, v) {
+                    return false;
+                }
+                self.mapping.iter_enumerated().all(|(u1, v1)| match v1 {
+                    Some(v1) => {
+                        self.edges_between(0, u, u1) == self.edges_between(1, v, *v1)
+                            && self.edges_between(0, u1, u) == self.edges_between(1, *v1, v)
+                    }
+                    None => true,
+                })
+            }
+
+            fn search(&mut self, u: usize) -> Option<bool> {
+                self.steps += 1;
+                if self.steps > MAX_ISOMORPHISM_STEPS {
+                    return None;
+                }
+                if u == self.mapping.len() {
+                    return Some(true);
+                }
+                let u = Local::from_usize(u);
+                if let Some(v) = self.mapping[u] {
+                    // pinned
+                    return if self.is_feasible_pinned(u, v) {
+                        self.search(u.as_usize() + 1)
+                    } else {
+                        Some(false)
+                    };
+                }
+                for v in self.is_used.indices() {
+                    if !self.is_feasible(u, v) {
+                        continue;
+                    }
+                    self.mapping[u] = Some(v);
+                    self.is_used[v] = true;
+                    if self.search(u.as_usize() + 1)? {
+                        return Some(true);
+                    }
+                    self.mapping[u] = None;
+                    self.is_used[v] = false;
+                }
+                Some(false)
+            }
+
+            // a pinned node has been mapped in advance, so it is checked against the nodes mapped before it
+            fn is_feasible_pinned(&mut self, u: Local, v: Local) -> bool {
+                self.mapping[u] = None;
+                self.is_used[v] = false;
+                let is_feasible =
+                    self.mapping
+                        .iter_enumerated()
+                        .take(u.as_usize())
+                        .all(|(u1, v1)| {
+                            v1.map_or(true, |v1| {
+                                self.edges_between(0, u, u1) == self.edges_between(1, v, v1)
+                                    && self.edges_between(0, u1, u) == self.edges_between(1, v1, v)
+                            })
+                        })
+                        && self.labels[0][u] == self.labels[1][v]
+                        && self.edges_between(0, u, u) == self.edges_between(1, v, v);
+                self.mapping[u] = Some(v);
+                self.is_used[v] = true;
+                is_feasible
+            }
+        }
+        let label = |graph: &Graph, node: &GraphNode| {
+            let ops = node
+                .ops
+                .iter()
+                .map(|op| match op {
+                    NodeOp::Const(idx) => format!("Const({})", graph.const_value(*idx)),
+                    NodeOp::Assert(_) => String::from(op.name()), //the message refers to the locals by index
+                    _ => format!("{:?}", op),
+                })
+                .collect();
+            (ops, node.in_edges.len(), node.out_edges.len())
+        };
+        fn edges(graph: &Graph) -> EdgesBetween<'_> {
+            let mut edges: EdgesBetween<'_> = HashMap::new();
+            for edge in graph.edges.iter() {
+                edges
+                    .entry((edge.src, edge.dst))
+                    .or_default()
+                    .push((&edge.op, edge.seq));
+            }
+            for pairs in edges.values_mut() {
+                pairs.sort_by_key(|(op, seq)| (format!("{:?}", op), *seq));
+            }
+            edges
+        }
+        let mut matcher = Matcher {
+            labels: [
+                self.nodes.iter().map(|node| label(self, node)).collect(),
+                other.nodes.iter().map(|node| label(other, node)).collect(),
+            ],
+            edges: [edges(self), edges(other)],
+            mapping: IndexVec::from_elem_n(None, self.nodes.len()),
+            is_used: IndexVec::from_elem_n(false, self.nodes.len()),
+            steps: 0,
+        };
+        for local in std::iter::once(self.return_local()).chain(self.params()) {
+            matcher.mapping[local] = Some(local);
+            matcher.is_used[local] = true;
+        }
+        matcher.search(0).unwrap_or(false)
+    }
+
+    // Renumber the nodes by the remap, and remove the nodes which are not mapped together with their edges.
+    // The remap is assumed to be compact and order-preserving, e.g., the one built by `LocalRemap::compact`.
+    // The side tables keyed by locals, i.e., the field names and the reference locals, are renumbered as well,
+    // and their entries of the removed nodes are dropped.
+    pub fn apply_remap(&mut self, remap: &LocalRemap) {
+        let mut edges = GraphEdges::new();
+        let mut edge_map: HashMap<EdgeIdx, EdgeIdx> = HashMap::new();
+        for (old_idx, edge) in self.edges.iter_enumerated() {
+            if let (Some(src), Some(dst)) = (remap.get(edge.src), remap.get(edge.dst)) {
+                let new_idx = edges.push(GraphEdge {
+                    src,
+                    dst,
+                    op: edge.op.clone(),
+                    seq: edge.seq,
+                    span: edge.span,
+                });
+                edge_map.insert(old_idx, new_idx);
+            }
+        }
+        let mut nodes = GraphNodes::new();
+        for (local, node) in self.nodes.iter_enumerated() {
+            if remap.get(local).is_some() {
+                let mut node = node.clone();
+                node.in_edges = node
+                    .in_edges
+                    .iter()
+                    .filter_map(|edge_idx| edge_map.get(edge_idx).copied())
+                    .collect();
+                node.out_edges = node
+                    .out_edges
+                    .iter()
+                    .filter_map(|edge_idx| edge_map.get(edge_idx).copied())
+                    .collect();
+                nodes.push(node);
+            }
+        }
+        self.n_locals = self
+            .nodes
+            .indices()
+            .filter(|local| local.as_usize() < self.n_locals && remap.get(*local).is_some())
+            .count();
+        self.nodes = nodes;
+        self.edges = edges;
+        self.field_names = std::mem::take(&mut self.field_names)
+            .into_iter()
+            .filter_map(|((local, projection), name)| Some(((remap.get(local)?, projection), name)))
+            .collect();
+        self.ref_locals = self
+            .ref_locals
+            .iter()
+            .filter_map(|local| remap.get(*local))
+            .collect();
+    }
+
+    // Collapse the chains of plain copies, e.g., `_2 = copy _1; _3 = move _2` becomes `_3 = move _1`,
+    // for the analyses which do not care about the temporaries. A local is collapsed into its source if it is
+    // assigned exactly once by a `Copy` or a `Move` from a source which is assigned at most once, so that both hold
+    // the same value; the return value and the parameters are always kept. The edges out of a collapsed local then
+    // start from the source of the chain with their ops unchanged, and the nodes are renumbered as `compact` does.
+    // The remap is returned as well to keep the external maps keyed by `Local` in sync.
+    pub fn contract_copies(&self) -> (Graph, LocalRemap) {
+        let is_collapsed: IndexVec<Local, bool> = self
+            .nodes
+            .iter_enumerated()
+            .map(|(local, node)| {
+                local.as_usize() < self.n_locals
+                    && local.as_usize() > self.argc
+                    && matches!(node.ops.as_slice(), [NodeOp::Use])
+                    && node.in_edges.len() == 1
+                    && matches!(self.edges[node.in_edges[0]].op, EdgeOp::Copy | EdgeOp::Move)
+                    && self.nodes[self.edges[node.in_edges[0]].src]
+                        .global_seq
+                        .len()
+                        <= 1
+            })
+            .collect();
+        // the source at the start of the chain, where each step visits a different local unless there is a cycle
+        let resolve = |mut local: Local| {
+            for _ in 0..self.n_locals {
+                if !is_collapsed[local] {
+                    break;
+                }
+                local = self.edges[self.nodes[local].in_edges[0]].src;
+            }
+            local
+        };
+        let mut graph = self.clone();
+        for edge in graph.edges.iter_mut() {
+            if !is_collapsed[edge.dst] {
+                edge.src = resolve(edge.src);
+            }
+        }
+        for node in graph.nodes.iter_mut() {
+            node.out_edges.clear();
+        }
+        for (edge_idx, edge) in self.edges.iter_enumerated() {
+            // the const nodes do not record their out_edges
+            if !matches!(self.nodes[edge.src].ops[0], NodeOp::Const(_)) {
+                let src = graph.edges[edge_idx].src;
+                graph.nodes[src].out_edges.push(edge_idx);
+            }
+        }
+        let remap = LocalRemap::compact(self, |local| !is_collapsed[local]);
+        graph.apply_remap(&remap);
+        (graph, remap)
+    }
+
+    // Get a copy without the orphan markers, i.e., the markers on no edge, so that the indices are dense
+    // for the consumers assuming so. The mir locals are all kept with their indices unchanged, while the markers
+    // are renumbered by the returned remap.
+    pub fn compact(&self) -> (Graph, LocalRemap) {
+        let mut is_connected: IndexVec<Local, bool> =
+            IndexVec::from_elem_n(false, self.nodes.len());
+        // the const nodes do not record their out_edges, so the edges are scanned instead
+        for edge in self.edges.iter() {
+            is_connected[edge.src] = true;
+            is_connected[edge.dst] = true;
+        }
+        let remap = LocalRemap::compact(self, |local| {
+            local.as_usize() < self.n_locals || is_connected[local]
+        });
+        let mut graph = self.clone();
+        graph.apply_remap(&remap);
+        (graph, remap)
+    }
+
+    // Remove the duplicated edges with the same (src, dst, op) in the same batch, e.g., those of `_1 + _1`,
+    // keeping the first one of them. The edges of different batches are kept since they come from different
+    // statements. Note that the edges are renumbered, so the `EdgeIdx`es obtained before are invalidated.
+    pub fn dedup_edges(&mut self) {
+        let mut edges = GraphEdges::new();
+        let mut edge_map: HashMap<EdgeIdx, EdgeIdx> = HashMap::new();
+        let mut first_edges: HashMap<(Local, Local, EdgeOp, usize), EdgeIdx> = HashMap::new();
+        for (old_idx, edge) in self.edges.iter_enumerated() {
+            let key = (edge.src, edge.dst, edge.op.clone(), edge.seq);
+            let new_idx = *first_edges
+                .entry(key)
+                .or_insert_with(|| edges.push(edge.clone()));
+            edge_map.insert(old_idx, new_idx);
+        }
+        for node in self.nodes.iter_mut() {
+            for edge_list in [&mut node.in_edges, &mut node.out_edges] {
+                let mut visited = HashSet::new();
+                *edge_list = edge_list
+                    .iter()
+                    .map(|edge_idx| edge_map[edge_idx])
+                    .filter(|edge_idx| visited.insert(*edge_idx))
+                    .collect();
+            }
+        }
+        self.edges = edges;
+    }
+
+    pub fn edges_iter(&self) -> impl Iterator<Item = &GraphEdge> {
+        self.edges.iter()
+    }
+
+    // the edges into the node in the order they are added, whose srcs are the operands writing the node
+    pub fn incoming(&self, local: Local) -> impl Iterator<Item = &GraphEdge> {
+        self.nodes[local]
+            .in_edges
+            .iter()
+            .map(|edge_idx| &self.edges[*edge_idx])
+    }
+
+    // the edges out of the node in the order they are added, whose dsts are the nodes reading the node
+    pub fn outgoing(&self, local: Local) -> impl Iterator<Item = &GraphEdge> {
+        self.nodes[local]
+            .out_edges
+            .iter()
+            .map(|edge_idx| &self.edges[*edge_idx])
+    }
 
     pub fn get_upside_idx(&self, node_idx: Local, order: usize) -> Option<Local> {
         if let Some(edge_idx) = self.nodes[node_idx].in_edges.get(order) {
@@ -518,7 +2769,9 @@ impl Graph {
 impl Graph {
     pub fn equivalent_edge_validator(graph: &Graph, idx: EdgeIdx) -> DFSStatus {
         match graph.edges[idx].op {
-            EdgeOp::Copy | EdgeOp::Move | EdgeOp::Mut | EdgeOp::Immut => DFSStatus::Continue,
+            EdgeOp::Copy | EdgeOp::Move | EdgeOp::Mut | EdgeOp::Immut | EdgeOp::CopyForDeref => {
+                DFSStatus::Continue
+            }
             EdgeOp::Nop
             | EdgeOp::Const
             | EdgeOp::Deref
@@ -527,7 +2780,18 @@ impl Graph {
             | EdgeOp::Index
             | EdgeOp::ConstIndex
             | EdgeOp::SubSlice
-            | EdgeOp::SubType => DFSStatus::Stop,
+            | EdgeOp::SubType
+            | EdgeOp::UnknownProjection(_)
+            | EdgeOp::SwitchValue(_) => DFSStatus::Stop,
+        }
+    }
+
+    // The value of the src is (a part of) the value of the dst, so a deref, which reads the pointee instead,
+    // and a matched value of a branch are not followed.
+    pub fn value_flow_edge_validator(graph: &Graph, idx: EdgeIdx) -> DFSStatus {
+        match graph.edges[idx].op {
+            EdgeOp::Nop | EdgeOp::Deref | EdgeOp::SwitchValue(_) => DFSStatus::Stop,
+            _ => DFSStatus::Continue,
         }
     }
 
@@ -536,37 +2800,164 @@ impl Graph {
     }
 }
 
-#[derive(Clone, Copy)]
-pub enum Direction {
-    Upside,
-    Downside,
-    Both,
+// How references are treated when collecting the equivalent locals, e.g., for `_2 = &_1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EquivalenceMode {
+    // A reference is transparent, i.e., it is in the same class as its referent (_1 and _2 are equivalent),
+    // which answers what a local may point to.
+    PointsTo,
+    // A reference is a value different from its referent (_1 and _2 are not equivalent),
+    // which answers which locals must hold the same value, e.g., for must-alias analyses.
+    ValueEqual,
 }
 
-pub enum DFSStatus {
-    Continue, // true
-    Stop,     // false
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OperandStats {
+    pub moves: usize,
+    pub copies: usize,
+    pub consts: usize, //the const loads
 }
 
-impl DFSStatus {
-    pub fn and(s1: DFSStatus, s2: DFSStatus) -> DFSStatus {
-        if matches!(s1, DFSStatus::Stop) || matches!(s2, DFSStatus::Stop) {
-            DFSStatus::Stop
-        } else {
-            DFSStatus::Continue
+// The map from the old locals to the new ones, returned by the transforms renumbering the nodes of a graph,
+// i.e., `Graph::compact` and `Graph::contract_copies`. The external maps keyed by `Local` can be kept in sync
+// with the transformed graph by `remap_keys`.
+#[derive(Clone, Debug)]
+pub struct LocalRemap {
+    map: IndexVec<Local, Option<Local>>, //None if the node is removed
+}
+
+impl LocalRemap {
+    // Keep the nodes satisfying `keep` in their original order.
+    // The return value and the parameters are always kept so that the signature is unchanged.
+    pub fn compact<F>(graph: &Graph, keep: F) -> Self
+    where
+        F: Fn(Local) -> bool,
+    {
+        let mut n_kept = 0;
+        let map = graph
+            .nodes
+            .indices()
+            .map(|local| {
+                if local.as_usize() <= graph.argc || keep(local) {
+                    n_kept += 1;
+                    Some(Local::from_usize(n_kept - 1))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Self { map }
+    }
+
+    pub fn get(&self, old: Local) -> Option<Local> {
+        self.map.get(old).copied().flatten()
+    }
+
+    // the entries of the removed nodes are dropped
+    pub fn remap_keys<T>(&self, values: HashMap<Local, T>) -> HashMap<Local, T> {
+        values
+            .into_iter()
+            .filter_map(|(old, value)| self.get(old).map(|new| (new, value)))
+            .collect()
+    }
+}
+
+// A side table for the downstream analyses to annotate the nodes of a graph, e.g., with a taint or a value range,
+// without changing `GraphNode`. It is kept in sync with the graph returned by `Graph::compact` or
+// `Graph::contract_copies` by passing the remap returned alongside to `remap`.
+#[derive(Clone, Debug)]
+pub struct GraphAnnotations<T> {
+    annotations: HashMap<Local, T>,
+}
+
+impl<T> GraphAnnotations<T> {
+    pub fn new() -> Self {
+        Self {
+            annotations: HashMap::new(),
         }
     }
 
-    pub fn or(s1: DFSStatus, s2: DFSStatus) -> DFSStatus {
-        if matches!(s1, DFSStatus::Continue) || matches!(s2, DFSStatus::Continue) {
-            DFSStatus::Continue
-        } else {
-            DFSStatus::Stop
+    pub fn get(&self, local: Local) -> Option<&T> {
+        self.annotations.get(&local)
+    }
+
+    pub fn get_mut(&mut self, local: Local) -> Option<&mut T> {
+        self.annotations.get_mut(&local)
+    }
+
+    // the old annotation is returned if any
+    pub fn set(&mut self, local: Local, annotation: T) -> Option<T> {
+        self.annotations.insert(local, annotation)
+    }
+
+    pub fn remove(&mut self, local: Local) -> Option<T> {
+        self.annotations.remove(&local)
+    }
+
+    // in an arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (Local, &T)> {
+        self.annotations
+            .iter()
+            .map(|(local, annotation)| (*local, annotation))
+    }
+
+    // the annotations of the removed nodes are dropped
+    pub fn remap(&mut self, remap: &LocalRemap) {
+        self.annotations = remap.remap_keys(std::mem::take(&mut self.annotations));
+    }
+}
+
+impl<T> Default for GraphAnnotations<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirectedGraph for Graph {
+    type Node = Local;
+    type Edge = EdgeIdx;
+
+    fn nodes(&self) -> Vec<Local> {
+        self.nodes.indices().collect()
+    }
+
+    fn edges(&self, node: Local, direction: Direction) -> Vec<(EdgeIdx, Local)> {
+        let node = &self.nodes[node];
+        let in_edges = node
+            .in_edges
+            .iter()
+            .map(|edge_idx| (*edge_idx, self.edges[*edge_idx].src));
+        let out_edges = node
+            .out_edges
+            .iter()
+            .map(|edge_idx| (*edge_idx, self.edges[*edge_idx].dst));
+        match direction {
+            Direction::Upside => in_edges.collect(),
+            Direction::Downside => out_edges.collect(),
+            Direction::Both => in_edges.chain(out_edges).collect(),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Index<EdgeIdx> for Graph {
+    type Output = GraphEdge;
+
+    fn index(&self, idx: EdgeIdx) -> &GraphEdge {
+        &self.edges[idx]
+    }
+}
+
+impl<'a> IntoIterator for &'a Graph {
+    type Item = &'a GraphEdge;
+    type IntoIter = std::slice::Iter<'a, GraphEdge>;
+
+    // iterate over the edges, e.g., `for edge in &graph`
+    fn into_iter(self) -> Self::IntoIter {
+        self.edges.iter()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash)]
 pub enum AggKind {
     Array,
     Tuple,
@@ -574,3 +2965,1079 @@ pub enum AggKind {
     Closure(DefId),
     Coroutine(DefId),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::with_tcx;
+    use rustc_hir::def_id::CRATE_DEF_ID;
+    use rustc_middle::mir::SourceInfo;
+
+    // a graph without mir, whose nodes are `_0`, the parameters, and the other locals
+    fn graph(argc: usize, n_locals: usize) -> Graph {
+        Graph::new(CRATE_DEF_ID.to_def_id(), DUMMY_SP, argc, n_locals)
+    }
+
+    fn local(idx: usize) -> Local {
+        Local::from_usize(idx)
+    }
+
+    // add a write batch of dst at the global seq as the builder does, whose sources are consts or locals
+    fn assign(graph: &mut Graph, srcs: &[Option<usize>], dst: usize, op: NodeOp, time: usize) {
+        let dst = local(dst);
+        let seq = graph.nodes[dst].seq;
+        if seq == graph.nodes[dst].ops.len() {
+            graph.nodes[dst].ops.push(NodeOp::Nop);
+        }
+        for src in srcs {
+            match src {
+                Some(src) => graph.add_node_edge(local(*src), dst, EdgeOp::Move),
+                None => graph.add_const_edge(time, DUMMY_SP, dst, EdgeOp::Const),
+            };
+        }
+        graph.nodes[dst].ops[seq] = op;
+        graph.nodes[dst].seq = seq + 1;
+        graph.nodes[dst].global_seq.push(time);
+    }
+
+    #[test]
+    fn test_intern_const_shares_values() {
+        let mut graph = graph(0, 2);
+        let one = graph.add_const_edge(1_i32, DUMMY_SP, local(0), EdgeOp::Const);
+        let another_one = graph.add_const_edge("1", DUMMY_SP, local(1), EdgeOp::Const);
+        let two = graph.add_const_edge(2_i32, DUMMY_SP, local(1), EdgeOp::Const);
+        let const_idx = |edge_idx: EdgeIdx| match graph.nodes[graph.edges[edge_idx].src].ops[0] {
+            NodeOp::Const(idx) => idx,
+            _ => panic!("not a const node"),
+        };
+        assert_eq!(const_idx(one), const_idx(another_one));
+        assert_ne!(const_idx(one), const_idx(two));
+        assert_eq!(graph.consts, vec!["1", "2"]);
+        assert_eq!(graph.const_value(const_idx(two)), "2");
+        let two = const_idx(two);
+        assert_eq!(graph.intern_const("2"), two);
+        assert_eq!(graph.consts.len(), 2);
+    }
+
+    #[test]
+    fn test_dead_locals_skips_call_destinations() {
+        // _2 = f(copy _1) -> (); _3 = copy _1;
+        let mut graph = graph(1, 4);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        graph.nodes[local(2)].ops[0] = NodeOp::Call(CRATE_DEF_ID.to_def_id());
+        graph.add_node_edge(local(1), local(3), EdgeOp::Copy);
+        graph.nodes[local(3)].ops[0] = NodeOp::Use;
+        assert_eq!(graph.dead_locals(), vec![local(3)]);
+    }
+
+    #[test]
+    fn test_compact_removes_orphan_markers() {
+        // _2 = copy _1; _0 = copy (*_2); with an orphan marker before the deref
+        let mut graph = graph(1, 3);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        let orphan = graph.nodes.push(GraphNode::new());
+        let deref = graph.nodes.push(GraphNode::new());
+        graph.add_node_edge(local(2), deref, EdgeOp::Deref);
+        graph.add_node_edge(deref, local(0), EdgeOp::Copy);
+        let (compacted, remap) = graph.compact();
+        assert_eq!(remap.get(orphan), None);
+        assert_eq!(remap.get(deref), Some(local(3)));
+        assert_eq!(compacted.nodes.len(), 4);
+        assert_eq!(compacted.n_locals, 3);
+        // no gaps: every marker left is on an edge
+        for (idx, node) in compacted.nodes.iter_enumerated() {
+            assert!(idx.as_usize() < compacted.n_locals || !node.in_edges.is_empty());
+        }
+        // structurally equal: the same edges between the remapped nodes
+        assert_eq!(compacted.edges.len(), graph.edges.len());
+        for (old, new) in graph.edges.iter().zip(compacted.edges.iter()) {
+            assert_eq!(remap.get(old.src), Some(new.src));
+            assert_eq!(remap.get(old.dst), Some(new.dst));
+            assert_eq!(old.op, new.op);
+        }
+        for (edge_idx, edge) in compacted.edges.iter_enumerated() {
+            assert!(compacted.nodes[edge.src].out_edges.contains(&edge_idx));
+            assert!(compacted.nodes[edge.dst].in_edges.contains(&edge_idx));
+        }
+    }
+
+    #[test]
+    fn test_contract_copies_collapses_chain() {
+        // _2 = copy _1; _3 = copy _2; _4 = copy _3; _0 = move _4;
+        let mut graph = graph(1, 5);
+        for (src, dst, op) in [
+            (1, 2, EdgeOp::Copy),
+            (2, 3, EdgeOp::Copy),
+            (3, 4, EdgeOp::Copy),
+            (4, 0, EdgeOp::Move),
+        ] {
+            graph.add_node_edge(local(src), local(dst), op);
+            graph.nodes[local(dst)].ops[0] = NodeOp::Use;
+        }
+        let (contracted, remap) = graph.contract_copies();
+        for collapsed in 2..5 {
+            assert_eq!(remap.get(local(collapsed)), None);
+        }
+        assert_eq!(remap.get(local(1)), Some(local(1)));
+        assert_eq!(contracted.nodes.len(), 2);
+        assert_eq!(contracted.edges.len(), 1);
+        let edge = &contracted.edges[0];
+        assert_eq!(
+            (edge.src, edge.dst, &edge.op),
+            (local(1), local(0), &EdgeOp::Move)
+        );
+        assert_eq!(contracted.nodes[local(1)].out_edges, vec![0]);
+        assert_eq!(contracted.nodes[local(0)].in_edges, vec![0]);
+    }
+
+    #[test]
+    fn test_annotations_follow_remap() {
+        // _2 = copy _1; _0 = move _2;
+        let mut graph = graph(1, 3);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        graph.nodes[local(2)].ops[0] = NodeOp::Use;
+        graph.add_node_edge(local(2), local(0), EdgeOp::Move);
+        graph.nodes[local(0)].ops[0] = NodeOp::Use;
+        let mut annotations = GraphAnnotations::new();
+        annotations.set(local(0), "ret");
+        annotations.set(local(1), "param");
+        annotations.set(local(2), "tmp");
+        let (contracted, remap) = graph.contract_copies();
+        annotations.remap(&remap);
+        assert_eq!(contracted.nodes.len(), 2);
+        assert_eq!(annotations.get(local(0)), Some(&"ret"));
+        assert_eq!(annotations.get(local(1)), Some(&"param"));
+        assert_eq!(annotations.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_returned_references_include_params() {
+        // fn f<T>(x: &T) -> &T { x }, i.e., _0 = copy _1;
+        let mut graph = graph(1, 2);
+        graph.add_node_edge(local(1), local(0), EdgeOp::Copy);
+        graph.nodes[local(0)].ops[0] = NodeOp::Use;
+        graph.set_ref_locals(HashSet::from([local(0), local(1)]));
+        assert_eq!(graph.returned_references(), vec![local(1)]);
+    }
+
+    #[test]
+    fn test_reaching_defs_of_reassigned_local() {
+        // _1 = const 0; _2 = move _1; _1 = const 2;
+        let mut graph = graph(0, 3);
+        assign(&mut graph, &[None], 1, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 1);
+        assign(&mut graph, &[None], 1, NodeOp::Use, 2);
+        assert_eq!(
+            graph.reaching_defs(local(1)),
+            vec![(local(1), 0), (local(1), 1)]
+        );
+        assert_eq!(graph.reaching_defs(local(2)), vec![(local(2), 0)]);
+    }
+
+    #[test]
+    fn test_use_after_move() {
+        // _2 = move _1; _3 = move _1; _1 = const 2; _4 = move _1;
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Use, 1);
+        assign(&mut graph, &[None], 1, NodeOp::Use, 2);
+        assign(&mut graph, &[Some(1)], 4, NodeOp::Use, 3);
+        // the read after the reassignment is fine
+        assert_eq!(graph.use_after_move(), vec![(local(1), 1)]);
+    }
+
+    #[test]
+    fn test_longest_path_skips_shortcut() {
+        // without any edge, the path is a single node
+        assert_eq!(graph(0, 1).longest_path(), Ok(vec![local(0)]));
+        // _2 = copy _1; _3 = copy _2; _0 = Add(_3, _1);
+        let mut graph = graph(1, 4);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2)], 3, NodeOp::Use, 1);
+        assign(&mut graph, &[Some(3), Some(1)], 0, NodeOp::Nop, 2);
+        assert_eq!(
+            graph.longest_path(),
+            Ok(vec![local(1), local(2), local(3), local(0)])
+        );
+    }
+
+    #[test]
+    fn test_path_count_of_diamond() {
+        // _2 = copy _1; _3 = copy _1; _0 = Add(_2, _3); _4 = Add(_1, _1);
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Use, 1);
+        assign(&mut graph, &[Some(2), Some(3)], 0, NodeOp::Nop, 2);
+        assign(&mut graph, &[Some(1), Some(1)], 4, NodeOp::Nop, 3);
+        assert_eq!(graph.path_count(local(1), local(0)), Ok(2));
+        // the parallel edges are different paths
+        assert_eq!(graph.path_count(local(1), local(4)), Ok(2));
+        assert_eq!(graph.path_count(local(2), local(0)), Ok(1));
+        assert_eq!(graph.path_count(local(0), local(1)), Ok(0));
+    }
+
+    #[test]
+    fn test_path_count_of_cycle() {
+        // _1 = copy _2; _2 = copy _1;
+        let mut graph = graph(0, 3);
+        assign(&mut graph, &[Some(2)], 1, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 1);
+        let mut cycle = graph.path_count(local(1), local(2)).unwrap_err();
+        cycle.sort_unstable();
+        assert_eq!(cycle, vec![local(1), local(2)]);
+        assert!(graph.longest_path().is_err());
+    }
+
+    #[test]
+    fn test_is_isomorphic_to_renamed_locals() {
+        // _2 = copy _1; _3 = copy _2; _0 = copy _3;
+        let mut graph_a = graph(1, 4);
+        assign(&mut graph_a, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph_a, &[Some(2)], 3, NodeOp::Use, 1);
+        assign(&mut graph_a, &[Some(3)], 0, NodeOp::Use, 2);
+        // the same chain through _3 first: _3 = copy _1; _2 = copy _3; _0 = copy _2;
+        let mut graph_b = graph(1, 4);
+        assign(&mut graph_b, &[Some(1)], 3, NodeOp::Use, 0);
+        assign(&mut graph_b, &[Some(3)], 2, NodeOp::Use, 1);
+        assign(&mut graph_b, &[Some(2)], 0, NodeOp::Use, 2);
+        assert!(graph_a.is_isomorphic_to(&graph_b));
+        assert!(graph_b.is_isomorphic_to(&graph_a));
+        // as many edges but a fork: _2 = copy _1; _3 = copy _1; _0 = copy _3;
+        let mut graph_c = graph(1, 4);
+        assign(&mut graph_c, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph_c, &[Some(1)], 3, NodeOp::Use, 1);
+        assign(&mut graph_c, &[Some(3)], 0, NodeOp::Use, 2);
+        assert!(!graph_a.is_isomorphic_to(&graph_c));
+    }
+
+    #[test]
+    fn test_identity_locals_of_copy_chain() {
+        // _2 = copy _1; _3 = copy _2; _0 = Add(_3, const 1); _4 = copy _1; _4 = copy _2;
+        let mut graph = graph(1, 5);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        graph.nodes[local(2)].ops[0] = NodeOp::Use;
+        graph.add_node_edge(local(2), local(3), EdgeOp::Copy);
+        graph.nodes[local(3)].ops[0] = NodeOp::Use;
+        assign(&mut graph, &[Some(3), None], 0, NodeOp::Nop, 2);
+        assign(&mut graph, &[Some(1)], 4, NodeOp::Use, 3);
+        assign(&mut graph, &[Some(2)], 4, NodeOp::Use, 4);
+        // _0 is not a plain use and _4 is assigned twice
+        assert_eq!(
+            graph.identity_locals(),
+            vec![(local(2), local(1)), (local(3), local(2))]
+        );
+    }
+
+    #[test]
+    fn test_remap_renumbers_side_tables() {
+        // _2 = copy _1; _3 = &((*_2).0: S).1; _0 = copy _3; where _1, _2, _3 are references
+        let mut graph = graph(1, 4);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        graph.nodes[local(2)].ops[0] = NodeOp::Use;
+        let deref = graph.nodes.push(GraphNode::new());
+        let field = graph.nodes.push(GraphNode::new());
+        graph.add_node_edge(local(2), deref, EdgeOp::Deref);
+        graph.add_node_edge(deref, field, EdgeOp::Field(String::from("x")));
+        graph.add_node_edge(field, local(3), EdgeOp::Immut);
+        graph.nodes[local(3)].ops[0] = NodeOp::Ref;
+        graph.add_node_edge(local(3), local(0), EdgeOp::Copy);
+        graph.nodes[local(0)].ops[0] = NodeOp::Use;
+        let field_idx =
+            |idx: usize| ProjectionElem::Field(rustc_target::abi::FieldIdx::from_usize(idx), ());
+        graph.set_field_names(HashMap::from([
+            (
+                (local(2), vec![ProjectionElem::Deref, field_idx(0)]),
+                String::from("x"),
+            ),
+            ((local(3), vec![field_idx(1)]), String::from("y")),
+        ]));
+        graph.set_ref_locals(HashSet::from([local(1), local(2), local(3)]));
+        let (contracted, remap) = graph.contract_copies();
+        assert_eq!(remap.get(local(2)), None);
+        assert_eq!(remap.get(local(3)), Some(local(2)));
+        // the entries of _2 are dropped and those of _3 are moved to its new index
+        assert_eq!(
+            contracted.field_names,
+            HashMap::from([((local(2), vec![field_idx(1)]), String::from("y"))])
+        );
+        assert_eq!(contracted.ref_locals, HashSet::from([local(1), local(2)]));
+        assert!(!contracted.is_ref_local(local(3)));
+        assert_eq!(contracted.returned_references(), vec![local(1), local(2)]);
+        assert!(contracted
+            .to_csv()
+            .lines()
+            .any(|line| line == "3,4,\"Field(\"\"x\"\")\",0"));
+    }
+
+    #[test]
+    fn test_returned_references_after_contract_copies() {
+        // fn f<T>(x: &T) -> &T { let y = x; y }, i.e., _2 = copy _1; _0 = copy _2;
+        let mut graph = graph(1, 3);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        graph.nodes[local(2)].ops[0] = NodeOp::Use;
+        graph.add_node_edge(local(2), local(0), EdgeOp::Copy);
+        graph.nodes[local(0)].ops[0] = NodeOp::Use;
+        graph.set_ref_locals(HashSet::from([local(0), local(1), local(2)]));
+        assert_eq!(graph.returned_references(), vec![local(1), local(2)]);
+        let (contracted, _) = graph.contract_copies();
+        assert_eq!(contracted.nodes.len(), 2);
+        assert_eq!(contracted.returned_references(), vec![local(1)]);
+        assert!(!contracted.is_ref_local(local(2)));
+    }
+
+    #[test]
+    fn test_dedup_edges_keeps_batches() {
+        // _2 = Add(move _1, move _1); _2 = move _1;
+        let mut graph = graph(1, 3);
+        assign(&mut graph, &[Some(1), Some(1)], 2, NodeOp::Nop, 0);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 1);
+        assert_eq!(graph.edges.len(), 3);
+        graph.dedup_edges();
+        let seqs: Vec<usize> = graph.incoming(local(2)).map(|edge| edge.seq).collect();
+        assert_eq!(seqs, vec![0, 1]);
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.nodes[local(1)].out_edges, vec![0, 1]);
+        assert_eq!(
+            graph.reaching_defs(local(2)),
+            vec![(local(2), 0), (local(2), 1)]
+        );
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn test_to_petgraph_keeps_cycles() {
+        // _1 = copy _2; _2 = copy _1; _0 = copy _2;
+        let mut cyclic = graph(0, 3);
+        assign(&mut cyclic, &[Some(2)], 1, NodeOp::Use, 0);
+        assign(&mut cyclic, &[Some(1)], 2, NodeOp::Use, 1);
+        assign(&mut cyclic, &[Some(2)], 0, NodeOp::Use, 2);
+        let (converted, indices) = cyclic.to_petgraph();
+        assert_eq!(converted.node_count(), 3);
+        assert_eq!(converted.edge_count(), 3);
+        assert!(converted.contains_edge(indices[&local(2)], indices[&local(0)]));
+        assert!(petgraph::algo::is_cyclic_directed(&converted));
+        // without the back edge, it is a DAG
+        let mut dag = graph(0, 3);
+        assign(&mut dag, &[Some(2)], 1, NodeOp::Use, 0);
+        assign(&mut dag, &[Some(2)], 0, NodeOp::Use, 1);
+        assert!(!petgraph::algo::is_cyclic_directed(&dag.to_petgraph().0));
+    }
+
+    #[test]
+    fn test_is_connected_on_cycle() {
+        // _1 = copy _2; _2 = copy _1; _3 is isolated
+        let mut graph = graph(0, 4);
+        assign(&mut graph, &[Some(2)], 1, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 1);
+        assert!(graph.is_connected(local(1), local(2)));
+        assert!(graph.is_connected(local(2), local(1)));
+        assert!(!graph.is_connected(local(1), local(3)));
+        // the methods of the graph and the generic algorithms see the same edges
+        assert_eq!(graph.successors(local(1)), vec![local(2)]);
+        assert_eq!(
+            dfs(&graph, local(1), Direction::Upside),
+            vec![local(1), local(2)]
+        );
+    }
+
+    #[test]
+    fn test_arg_dependent_locals_skips_deref() {
+        // _2 = copy _1; _3 = copy (*_1); _4 = const;
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        let deref = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(local(1), deref, EdgeOp::Deref);
+        graph.add_node_edge(deref, local(3), EdgeOp::Copy);
+        assign(&mut graph, &[None], 4, NodeOp::Use, 2);
+        let mut dependent: Vec<Local> = graph.arg_dependent_locals().into_iter().collect();
+        dependent.sort_unstable();
+        assert_eq!(dependent, vec![local(1), local(2)]);
+    }
+
+    #[test]
+    fn test_longest_path_skips_deref() {
+        // _2 = copy _1; _3 = copy (*_2); _0 = copy _3;
+        let mut graph = graph(1, 4);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        let deref = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(local(2), deref, EdgeOp::Deref);
+        graph.add_node_edge(deref, local(3), EdgeOp::Copy);
+        assign(&mut graph, &[Some(3)], 0, NodeOp::Use, 1);
+        // the chain through the deref is longer, but it does not carry the value of _1
+        assert_eq!(graph.longest_path(), Ok(vec![deref, local(3), local(0)]));
+    }
+
+    #[test]
+    fn test_path_count_skips_deref() {
+        // _2 = copy _1; _0 = Add(_2, copy (*_1));
+        let mut graph = graph(1, 3);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        let deref = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(local(1), deref, EdgeOp::Deref);
+        assign(&mut graph, &[Some(2)], 0, NodeOp::Nop, 1);
+        graph.add_node_edge(deref, local(0), EdgeOp::Copy);
+        assert_eq!(graph.path_count(local(1), local(0)), Ok(1));
+        assert_eq!(graph.path_count(deref, local(0)), Ok(1));
+    }
+
+    #[test]
+    fn test_with_capacity_reserves_edges() {
+        let graph = Graph::with_capacity(CRATE_DEF_ID.to_def_id(), DUMMY_SP, 1, 3, 16);
+        assert!(graph.edges.raw.capacity() >= 16);
+        assert!(graph.edges.is_empty());
+        assert_eq!(graph.nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_assert_acyclic_returns_cycle_members() {
+        // _2 = copy _1; _3 = copy _2; _4 = copy _3;
+        let mut graph = graph(0, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2)], 3, NodeOp::Use, 1);
+        assign(&mut graph, &[Some(3)], 4, NodeOp::Use, 2);
+        assert_eq!(graph.assert_acyclic(), Ok(()));
+        // _1 = copy _3; _0 = copy _3;
+        assign(&mut graph, &[Some(3)], 1, NodeOp::Use, 3);
+        assign(&mut graph, &[Some(3)], 0, NodeOp::Use, 4);
+        // the nodes only reaching or reached from the cycle are not its members
+        let mut cycle = graph.assert_acyclic().unwrap_err();
+        cycle.sort_unstable();
+        assert_eq!(cycle, vec![local(1), local(2), local(3)]);
+    }
+
+    #[test]
+    fn test_unknown_projection_keeps_building() {
+        with_tcx("unknown_projection", "", |tcx| {
+            // _0 = copy (_1 as u32), where the opaque cast is not modeled
+            let mut graph = graph(1, 2);
+            let place = Place {
+                local: local(1),
+                projection: tcx.mk_place_elems(&[PlaceElem::OpaqueCast(tcx.types.u32)]),
+            };
+            let statement = Statement {
+                source_info: SourceInfo::outermost(DUMMY_SP),
+                kind: StatementKind::Assign(Box::new((
+                    Place::from(local(0)),
+                    Rvalue::Use(Operand::Copy(place)),
+                ))),
+            };
+            graph.add_statm_to_graph(&statement);
+            assert!(matches!(
+                graph.errors[..],
+                [GraphBuildError::UnsupportedProjection(_)]
+            ));
+            // the cast is a marker between _1 and _0
+            let marker = graph.edges[graph.nodes[local(0)].in_edges[0]].src;
+            assert!(marker.as_usize() >= graph.n_locals);
+            let projection = &graph.edges[graph.nodes[marker].in_edges[0]];
+            assert_eq!(projection.src, local(1));
+            assert!(matches!(projection.op, EdgeOp::UnknownProjection(_)));
+            assert!(matches!(graph.nodes[local(0)].ops[..], [NodeOp::Use]));
+        });
+    }
+
+    #[test]
+    fn test_edges_by_index_and_iteration() {
+        // _2 = copy _1; _0 = Add(_2, _1);
+        let mut graph = graph(1, 3);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2), Some(1)], 0, NodeOp::Nop, 1);
+        let pairs: Vec<(Local, Local)> = (&graph)
+            .into_iter()
+            .map(|edge| (edge.src, edge.dst))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (local(1), local(2)),
+                (local(2), local(0)),
+                (local(1), local(0))
+            ]
+        );
+        let mut n_edges = 0;
+        for edge in &graph {
+            assert_eq!(edge.dst, graph[n_edges].dst);
+            n_edges += 1;
+        }
+        assert_eq!(n_edges, graph.edges_iter().count());
+        assert_eq!(graph[1].src, local(2));
+    }
+
+    #[test]
+    fn test_incoming_and_outgoing_edges() {
+        // _2 = copy _1; _0 = Add(_2, _1);
+        let mut graph = graph(1, 3);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2), Some(1)], 0, NodeOp::Nop, 1);
+        let srcs: Vec<Local> = graph.incoming(local(0)).map(|edge| edge.src).collect();
+        assert_eq!(srcs, vec![local(2), local(1)]);
+        let dsts: Vec<Local> = graph.outgoing(local(1)).map(|edge| edge.dst).collect();
+        assert_eq!(dsts, vec![local(2), local(0)]);
+        assert_eq!(graph.incoming(local(1)).count(), 0);
+        assert_eq!(graph.outgoing(local(0)).count(), 0);
+    }
+
+    #[test]
+    fn test_propagate_decays_below_threshold() {
+        // _2 = copy _1; _3 = copy (*_2); _1 = copy _2;
+        let mut graph = graph(1, 4);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        let deref = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(local(2), deref, EdgeOp::Deref);
+        graph.add_node_edge(deref, local(3), EdgeOp::Copy);
+        graph.add_node_edge(local(2), local(1), EdgeOp::Copy);
+        let edge_weight = |op: &EdgeOp| match op {
+            EdgeOp::Deref => 0.5,
+            _ => 0.8,
+        };
+        let scores = graph.propagate(&[local(1)], edge_weight, 0.6);
+        // the deref drops the score of the marker to 0.4, and the cycle never raises the source over 1.0
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[&local(1)], 1.0);
+        assert!((scores[&local(2)] - 0.8).abs() < 1e-6);
+        // without the threshold, the score keeps decaying along the path
+        let scores = graph.propagate(&[local(1)], edge_weight, 0.0);
+        assert!((scores[&local(3)] - 0.32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_neighbors_by_direction() {
+        // _2 = copy _1; _3 = copy _2;
+        let mut graph = graph(1, 4);
+        let in_edge = graph.add_node_edge(local(1), local(2), EdgeOp::Copy);
+        let out_edge = graph.add_node_edge(local(2), local(3), EdgeOp::Copy);
+        let neighbors = |direction| graph.neighbors(local(2), direction).collect::<Vec<_>>();
+        assert_eq!(neighbors(Direction::Upside), vec![(in_edge, local(1))]);
+        assert_eq!(neighbors(Direction::Downside), vec![(out_edge, local(3))]);
+        assert_eq!(
+            neighbors(Direction::Both),
+            vec![(in_edge, local(1)), (out_edge, local(3))]
+        );
+        assert_eq!(graph.neighbors(local(0), Direction::Both).count(), 0);
+    }
+
+    #[test]
+    fn test_connected_targets_in_one_traversal() {
+        // _2 = copy _1; _0 = copy _2; _3 = copy _4;
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2)], 0, NodeOp::Use, 1);
+        assign(&mut graph, &[Some(4)], 3, NodeOp::Use, 2);
+        let targets = [local(0), local(1), local(3), local(2)];
+        assert_eq!(
+            graph.connected_targets(local(1), &targets, Direction::Downside),
+            vec![true, true, false, true]
+        );
+        assert_eq!(
+            graph.connected_targets(local(2), &targets, Direction::Upside),
+            vec![false, true, false, true]
+        );
+        assert_eq!(
+            graph.connected_targets(local(3), &targets, Direction::Both),
+            vec![false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_params_and_return_local() {
+        let graph_of_three = graph(3, 6);
+        assert_eq!(
+            graph_of_three.params().collect::<Vec<_>>(),
+            vec![local(1), local(2), local(3)]
+        );
+        assert_eq!(graph_of_three.return_local(), local(0));
+        assert_eq!(graph(0, 1).params().count(), 0);
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_edge_order_and_const_idx() {
+        // _1 = const 2; _0 = Add(_1, _2);
+        let mut graph_a = graph(1, 3);
+        graph_a.add_const_edge(2, DUMMY_SP, local(1), EdgeOp::Const);
+        graph_a.add_node_edge(local(1), local(0), EdgeOp::Copy);
+        graph_a.add_node_edge(local(2), local(0), EdgeOp::Copy);
+        // the same statements with the edges into _0 added the other way round,
+        // and another const interned first so that the idx of 2 differs
+        let mut graph_b = graph(1, 3);
+        graph_b.intern_const("3");
+        graph_b.add_const_edge(2, DUMMY_SP, local(1), EdgeOp::Const);
+        graph_b.add_node_edge(local(2), local(0), EdgeOp::Copy);
+        graph_b.add_node_edge(local(1), local(0), EdgeOp::Copy);
+        assert_eq!(graph_a.fingerprint(), graph_b.fingerprint());
+        // a move instead of a copy
+        let mut graph_c = graph(1, 3);
+        graph_c.add_const_edge(2, DUMMY_SP, local(1), EdgeOp::Const);
+        graph_c.add_node_edge(local(1), local(0), EdgeOp::Move);
+        graph_c.add_node_edge(local(2), local(0), EdgeOp::Copy);
+        assert_ne!(graph_a.fingerprint(), graph_c.fingerprint());
+    }
+
+    #[test]
+    fn test_multiple_moves() {
+        // _2 = move _1; _3 = move _1; _0 = Add(copy _2, copy _2);
+        let mut graph = graph(1, 4);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Use, 1);
+        graph.add_node_edge(local(2), local(0), EdgeOp::Copy);
+        graph.add_node_edge(local(2), local(0), EdgeOp::Copy);
+        // the copies are not moves
+        assert_eq!(graph.multiple_moves(), vec![local(1)]);
+    }
+
+    #[test]
+    fn test_const_only_locals() {
+        // _2 = Add(const, const); _3 = copy _2; _4 = Add(copy _3, copy _1); _5 = &_2; _6 = call(const);
+        let mut graph = graph(1, 7);
+        assign(&mut graph, &[None, None], 2, NodeOp::Nop, 0);
+        assign(&mut graph, &[Some(2)], 3, NodeOp::Use, 1);
+        assign(&mut graph, &[Some(3), Some(1)], 4, NodeOp::Nop, 2);
+        assign(&mut graph, &[Some(2)], 5, NodeOp::Ref, 3);
+        graph.edges[graph.nodes[local(5)].in_edges[0]].op = EdgeOp::Immut;
+        assign(
+            &mut graph,
+            &[None],
+            6,
+            NodeOp::Call(CRATE_DEF_ID.to_def_id()),
+            4,
+        );
+        // the parameter taints _4, and the borrow and the call are not values of consts
+        assert_eq!(graph.const_only_locals(), vec![local(2), local(3)]);
+    }
+
+    #[test]
+    fn test_has_side_effect_ops() {
+        // _2 = Add(copy _1, const); _0 = copy _2;
+        let mut pure = graph(1, 4);
+        assign(&mut pure, &[Some(1), None], 2, NodeOp::Nop, 0);
+        assign(&mut pure, &[Some(2)], 0, NodeOp::Use, 1);
+        assert!(!pure.has_side_effect_ops());
+        // _3 = &mut _2;
+        let mut borrowing = pure.clone();
+        assign(&mut borrowing, &[Some(2)], 3, NodeOp::Ref, 2);
+        assert!(!borrowing.has_side_effect_ops());
+        borrowing.edges[borrowing.nodes[local(3)].in_edges[0]].op = EdgeOp::Mut;
+        assert!(borrowing.has_side_effect_ops());
+        // _3 = call(copy _2);
+        let mut calling = pure;
+        assign(
+            &mut calling,
+            &[Some(2)],
+            3,
+            NodeOp::Call(CRATE_DEF_ID.to_def_id()),
+            2,
+        );
+        assert!(calling.has_side_effect_ops());
+    }
+
+    #[test]
+    fn test_return_relevant_locals() {
+        // _2 = copy _1; _3 = Add(copy _2, const); _0 = copy _3; _4 = copy _1;
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2), None], 3, NodeOp::Nop, 1);
+        assign(&mut graph, &[Some(3)], 0, NodeOp::Use, 2);
+        assign(&mut graph, &[Some(1)], 4, NodeOp::Use, 3);
+        let mut relevant: Vec<Local> = graph.return_relevant_locals().into_iter().collect();
+        relevant.sort_unstable();
+        // the const node is not a mir local, and _4 is only a side branch of _1
+        assert_eq!(relevant, vec![local(0), local(1), local(2), local(3)]);
+    }
+
+    #[test]
+    fn test_call_arguments_of_last_call() {
+        // _3 = copy _1; _3 = call(copy _2, const); _0 = copy _3;
+        let mut graph = graph(2, 4);
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Use, 0);
+        let call = NodeOp::Call(CRATE_DEF_ID.to_def_id());
+        assign(&mut graph, &[Some(2), None], 3, call, 1);
+        assign(&mut graph, &[Some(3)], 0, NodeOp::Use, 2);
+        let const_node = graph.edges[graph.nodes[local(3)].in_edges[2]].src;
+        // the write before the call is not an argument
+        assert_eq!(graph.call_arguments(local(3)), vec![local(2), const_node]);
+        assert!(graph.call_arguments(local(0)).is_empty());
+    }
+
+    #[test]
+    fn test_deref_depth_takes_fewest_derefs() {
+        // _2 = copy (*(*_1)); _3 = Add(copy (*_1), copy _4); _5 = const;
+        let mut graph = graph(1, 6);
+        let deref_once = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(local(1), deref_once, EdgeOp::Deref);
+        let deref_twice = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(deref_once, deref_twice, EdgeOp::Deref);
+        graph.add_node_edge(deref_twice, local(2), EdgeOp::Copy);
+        graph.add_node_edge(deref_once, local(3), EdgeOp::Copy);
+        graph.add_node_edge(local(4), local(3), EdgeOp::Copy);
+        assign(&mut graph, &[None], 5, NodeOp::Use, 0);
+        assert_eq!(graph.deref_depth(local(2)), 2);
+        assert_eq!(graph.deref_depth(deref_once), 1);
+        // `_4` is a root without any deref
+        assert_eq!(graph.deref_depth(local(3)), 0);
+        assert_eq!(graph.deref_depth(local(5)), 0);
+    }
+
+    #[test]
+    fn test_collect_equivalent_locals_into_reused_set() {
+        // _2 = copy _1; _3 = copy _2; _4 = copy _1 as u64; _6 = copy _5;
+        let mut graph = graph(1, 7);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(2)], 3, NodeOp::Use, 1);
+        assign(&mut graph, &[Some(1)], 4, NodeOp::Cast, 2);
+        assign(&mut graph, &[Some(5)], 6, NodeOp::Use, 3);
+        let mut set = HashSet::new();
+        graph.collect_equivalent_locals_into(local(3), true, &mut set);
+        assert_eq!(set, HashSet::from([local(1), local(2), local(3)]));
+        assert_eq!(set, graph.collect_equivalent_locals(local(3), true));
+        // the locals already in the set are skipped, and the others are added into it
+        graph.collect_equivalent_locals_into(local(2), true, &mut set);
+        assert_eq!(set.len(), 3);
+        graph.collect_equivalent_locals_into(local(6), true, &mut set);
+        assert_eq!(
+            set,
+            HashSet::from([local(1), local(2), local(3), local(5), local(6)])
+        );
+    }
+
+    #[test]
+    fn test_equivalence_classes_partition_locals() {
+        // _2 = copy _1; _3 = copy _1 as u64; _4 = &_3; _0 = copy _4;
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Cast, 1);
+        assign(&mut graph, &[Some(3)], 4, NodeOp::Ref, 2);
+        graph.edges[graph.nodes[local(4)].in_edges[0]].op = EdgeOp::Immut;
+        assign(&mut graph, &[Some(4)], 0, NodeOp::Use, 3);
+        // the cast starts a new value, and the reference is in the class of its referent
+        assert_eq!(
+            graph.equivalence_classes(),
+            vec![
+                HashSet::from([local(0), local(3), local(4)]),
+                HashSet::from([local(1), local(2)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operand_stats_counts_edges_by_op() {
+        // _2 = copy _1; _3 = Add(move _2, const 1); _4 = copy _3; _0 = Add(move _4, const 2);
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Use, 0);
+        graph.edges[graph.nodes[local(2)].in_edges[0]].op = EdgeOp::Copy;
+        assign(
+            &mut graph,
+            &[Some(2), None],
+            3,
+            NodeOp::BinaryOp(BinOp::Add),
+            1,
+        );
+        assign(&mut graph, &[Some(3)], 4, NodeOp::Use, 2);
+        graph.edges[graph.nodes[local(4)].in_edges[0]].op = EdgeOp::Copy;
+        assign(
+            &mut graph,
+            &[Some(4), None],
+            0,
+            NodeOp::BinaryOp(BinOp::Add),
+            3,
+        );
+        let stats = graph.operand_stats();
+        assert_eq!((stats.moves, stats.copies, stats.consts), (2, 2, 2));
+        // the other ops, e.g., borrows, are not counted
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Ref, 4);
+        graph.edges[graph.nodes[local(2)].in_edges[1]].op = EdgeOp::Immut;
+        let stats = graph.operand_stats();
+        assert_eq!((stats.moves, stats.copies, stats.consts), (2, 2, 2));
+    }
+
+    #[test]
+    fn test_node_at_prefers_narrowest_span() {
+        let span = |lo: u32, hi: u32| Span::with_root_ctxt(BytePos(lo), BytePos(hi));
+        let mut graph = graph(1, 4);
+        graph.nodes[local(0)].span = span(0, 40);
+        graph.nodes[local(1)].span = span(10, 30);
+        graph.nodes[local(2)].span = span(12, 20);
+        // _3 keeps the dummy span, and the markers are never returned
+        let marker = graph.add_marker_node(NodeOp::InlineAsm, span(14, 15));
+        assert!(marker.as_usize() >= graph.n_locals);
+        assert_eq!(graph.node_at(BytePos(14)), Some(local(2)));
+        assert_eq!(graph.node_at(BytePos(25)), Some(local(1)));
+        assert_eq!(graph.node_at(BytePos(35)), Some(local(0)));
+        // the hi of a span is exclusive
+        assert_eq!(graph.node_at(BytePos(40)), None);
+        // the smaller local wins a tie
+        graph.nodes[local(3)].span = span(12, 20);
+        assert_eq!(graph.node_at(BytePos(14)), Some(local(2)));
+    }
+
+    #[test]
+    fn test_equivalence_modes_of_reference() {
+        // let r = &x; let s = r; i.e., _2 = &_1; _3 = copy _2;
+        let mut graph = graph(1, 4);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Ref, 0);
+        graph.edges[graph.nodes[local(2)].in_edges[0]].op = EdgeOp::Immut;
+        assign(&mut graph, &[Some(2)], 3, NodeOp::Use, 1);
+        let class_of = |local: Local, mode: EquivalenceMode| {
+            let mut set = HashSet::new();
+            graph.collect_equivalent_locals_in_mode(local, true, mode, &mut set);
+            set
+        };
+        // a reference points to its referent
+        let points_to = HashSet::from([local(1), local(2), local(3)]);
+        assert_eq!(class_of(local(3), EquivalenceMode::PointsTo), points_to);
+        assert_eq!(class_of(local(1), EquivalenceMode::PointsTo), points_to);
+        assert_eq!(graph.collect_equivalent_locals(local(3), true), points_to);
+        // but holds a different value
+        let value_equal = HashSet::from([local(2), local(3)]);
+        assert_eq!(class_of(local(3), EquivalenceMode::ValueEqual), value_equal);
+        assert_eq!(class_of(local(2), EquivalenceMode::ValueEqual), value_equal);
+        assert_eq!(
+            class_of(local(1), EquivalenceMode::ValueEqual),
+            HashSet::from([local(1)])
+        );
+    }
+
+    #[test]
+    fn test_may_alias_of_borrows() {
+        // _3 = &_1; _4 = &_1; _5 = &_2; _0 = Add(copy _1, copy _2);
+        let mut graph = graph(2, 6);
+        for (referent, reference, time) in [(1, 3, 0), (1, 4, 1), (2, 5, 2)] {
+            assign(&mut graph, &[Some(referent)], reference, NodeOp::Ref, time);
+            graph.edges[graph.nodes[local(reference)].in_edges[0]].op = EdgeOp::Immut;
+        }
+        assign(
+            &mut graph,
+            &[Some(1), Some(2)],
+            0,
+            NodeOp::BinaryOp(BinOp::Add),
+            3,
+        );
+        assert!(graph.may_alias(local(3), local(4)));
+        assert!(graph.may_alias(local(1), local(3)));
+        // the operands of an arithmetic operation are not unified
+        assert!(!graph.may_alias(local(3), local(5)));
+        assert!(!graph.may_alias(local(0), local(1)));
+        let classes = graph.alias_classes();
+        assert_eq!(classes[local(4)], local(1));
+        assert_eq!(classes[local(5)], local(2));
+    }
+
+    #[test]
+    fn test_roots_and_sinks() {
+        // _2 = const 1; _3 = Add(copy _1, copy _2); _0 = copy _3; _4 = copy _1; drop(_4);
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[None], 2, NodeOp::Use, 0);
+        assign(
+            &mut graph,
+            &[Some(1), Some(2)],
+            3,
+            NodeOp::BinaryOp(BinOp::Add),
+            1,
+        );
+        assign(&mut graph, &[Some(3)], 0, NodeOp::Use, 2);
+        assign(&mut graph, &[Some(1)], 4, NodeOp::Use, 3);
+        let drop = graph.add_marker_node(NodeOp::Drop, DUMMY_SP);
+        graph.add_node_edge(local(4), drop, EdgeOp::Move);
+        // the parameter and the local initialized by a const, but not the const marker
+        assert_eq!(graph.roots(), vec![local(1), local(2)]);
+        // the return value and the local which is only dropped, but not the drop marker
+        assert_eq!(graph.sinks(), vec![local(0), local(4)]);
+    }
+
+    #[test]
+    fn test_flows_sanitized_stops_at_sanitizer() {
+        // _2 = copy _1 as u64; _0 = Add(copy _2, const 1); _3 = copy _1; _4 = Sub(copy _3, const 1);
+        let mut graph = graph(1, 5);
+        assign(&mut graph, &[Some(1)], 2, NodeOp::Cast, 0);
+        assign(
+            &mut graph,
+            &[Some(2), None],
+            0,
+            NodeOp::BinaryOp(BinOp::Add),
+            1,
+        );
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Use, 2);
+        assign(
+            &mut graph,
+            &[Some(3), None],
+            4,
+            NodeOp::BinaryOp(BinOp::Sub),
+            3,
+        );
+        // the parameter is the only source, since the const markers are not `Nop`
+        let is_source = |op: &NodeOp| matches!(op, NodeOp::Nop);
+        let is_sink = |op: &NodeOp| matches!(op, NodeOp::BinaryOp(_));
+        assert_eq!(
+            graph.flows_sanitized(is_source, is_sink, |_| false),
+            vec![(local(1), local(0)), (local(1), local(4))]
+        );
+        // the cast sanitizes the flow into _0
+        assert_eq!(
+            graph.flows_sanitized(is_source, is_sink, |op| matches!(op, NodeOp::Cast)),
+            vec![(local(1), local(4))]
+        );
+    }
+
+    #[test]
+    fn test_constant_influence_partial_and_full() {
+        // x + 3 and 2 + 3, i.e., _2 = Add(copy _1, const 3); _3 = Add(const 2, const 3); _0 = copy _3;
+        let mut graph = graph(1, 4);
+        assign(
+            &mut graph,
+            &[Some(1), None],
+            2,
+            NodeOp::BinaryOp(BinOp::Add),
+            0,
+        );
+        assign(
+            &mut graph,
+            &[None, None],
+            3,
+            NodeOp::BinaryOp(BinOp::Add),
+            1,
+        );
+        assign(&mut graph, &[Some(3)], 0, NodeOp::Use, 2);
+        assert!(graph.constant_influence(local(2)));
+        assert!(!graph.only_constant_influence(local(2)));
+        assert!(graph.constant_influence(local(3)));
+        assert!(graph.only_constant_influence(local(3)));
+        // through the copy
+        assert!(graph.only_constant_influence(local(0)));
+        // the parameter is influenced by nothing
+        assert!(!graph.constant_influence(local(1)));
+        assert!(!graph.only_constant_influence(local(1)));
+    }
+
+    #[test]
+    fn test_most_direct_path_avoids_projections() {
+        // _1 -Deref-> _2 -> _0 has fewer edges, while _1 -> _3 -> _4 -> _0 has no projections
+        let mut graph = graph(1, 6);
+        graph.add_node_edge(local(1), local(2), EdgeOp::Deref);
+        graph.add_node_edge(local(2), local(0), EdgeOp::Move);
+        graph.add_node_edge(local(1), local(3), EdgeOp::Copy);
+        graph.add_node_edge(local(3), local(4), EdgeOp::Move);
+        graph.add_node_edge(local(4), local(0), EdgeOp::Move);
+        assert_eq!(
+            graph.most_direct_path(local(1), local(0)),
+            Some(vec![local(1), local(3), local(4), local(0)])
+        );
+        // counting the edges instead prefers the shorter one
+        assert_eq!(
+            graph.shortest_path_by(local(1), local(0), |_| 1),
+            Some(vec![local(1), local(2), local(0)])
+        );
+        assert_eq!(
+            graph.most_direct_path(local(1), local(1)),
+            Some(vec![local(1)])
+        );
+        assert_eq!(graph.most_direct_path(local(1), local(5)), None);
+    }
+
+    #[test]
+    fn test_dfs_skip_markers_only_visits_locals() {
+        // _2 = copy (*_1); _0 = move _2; where `*_1` is the marker _3
+        let mut graph = graph(1, 3);
+        let deref = graph.add_marker_node(NodeOp::Nop, DUMMY_SP);
+        graph.add_node_edge(local(1), deref, EdgeOp::Deref);
+        graph.add_node_edge(deref, local(2), EdgeOp::Copy);
+        graph.add_node_edge(local(2), local(0), EdgeOp::Move);
+        let visit = |skip_markers: bool| {
+            let mut visited = vec![];
+            let mut node_operator = |_: &Graph, idx: Local| -> DFSStatus {
+                visited.push(idx);
+                DFSStatus::Continue
+            };
+            let mut edge_validator = Graph::always_true_edge_validator;
+            if skip_markers {
+                graph.dfs_skip_markers(
+                    local(1),
+                    Direction::Downside,
+                    &mut node_operator,
+                    &mut edge_validator,
+                    true,
+                );
+            } else {
+                graph.dfs(
+                    local(1),
+                    Direction::Downside,
+                    &mut node_operator,
+                    &mut edge_validator,
+                    true,
+                );
+            }
+            visited
+        };
+        assert_eq!(visit(false), vec![local(1), deref, local(2), local(0)]);
+        assert_eq!(visit(true), vec![local(1), local(2), local(0)]);
+        // the edge validator still applies to the edges around the marker
+        let mut visited = vec![];
+        graph.dfs_skip_markers(
+            local(1),
+            Direction::Downside,
+            &mut |_: &Graph, idx: Local| {
+                visited.push(idx);
+                DFSStatus::Continue
+            },
+            &mut Graph::value_flow_edge_validator,
+            true,
+        );
+        assert_eq!(visited, vec![local(1)]);
+    }
+
+    #[test]
+    fn test_walk_orders_of_value_tree() {
+        // _3 = copy _1; _2 = copy (*_5); _4 = copy _2; _0 = Add(move _3, move _4); _1 = copy _0;
+        let mut graph = graph(2, 6);
+        assign(&mut graph, &[Some(1)], 3, NodeOp::Use, 0);
+        assign(&mut graph, &[Some(5)], 2, NodeOp::Use, 1);
+        graph.edges[graph.nodes[local(2)].in_edges[0]].op = EdgeOp::Deref;
+        assign(&mut graph, &[Some(2)], 4, NodeOp::Use, 2);
+        assign(
+            &mut graph,
+            &[Some(3), Some(4)],
+            0,
+            NodeOp::BinaryOp(BinOp::Add),
+            3,
+        );
+        // the back-edge of the cycle through _1
+        assign(&mut graph, &[Some(0)], 1, NodeOp::Use, 4);
+        let mut postorder = vec![];
+        graph.walk_postorder(local(0), Direction::Upside, &mut |_, idx| {
+            postorder.push(idx)
+        });
+        // the operands are visited before the values computed from them, and the deref is not followed
+        assert_eq!(
+            postorder,
+            vec![local(1), local(3), local(2), local(4), local(0)]
+        );
+        let mut preorder = vec![];
+        graph.walk_preorder(local(0), Direction::Upside, &mut |_, idx| {
+            preorder.push(idx)
+        });
+        assert_eq!(
+            preorder,
+            vec![local(0), local(3), local(1), local(4), local(2)]
+        );
+        // each node is entered and exited once, and its subtree is walked in between
+        let events = std::cell::RefCell::new(vec![]);
+        graph.walk(
+            local(3),
+            Direction::Upside,
+            &mut |_, idx| events.borrow_mut().push(("enter", idx)),
+            &mut |_, idx| events.borrow_mut().push(("exit", idx)),
+        );
+        assert_eq!(
+            events.into_inner(),
+            vec![
+                ("enter", local(3)),
+                ("enter", local(1)),
+                ("enter", local(0)),
+                ("enter", local(4)),
+                ("enter", local(2)),
+                ("exit", local(2)),
+                ("exit", local(4)),
+                ("exit", local(0)),
+                ("exit", local(1)),
+                ("exit", local(3)),
+            ]
+        );
+    }
+}
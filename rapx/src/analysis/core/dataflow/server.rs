@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+
+use rustc_middle::mir::Local;
+use serde_json::{json, Value};
+
+use super::graph::{Direction, Graph};
+use crate::analysis::core::graph::dfs;
+
+// A line-based query layer over a built graph for the integrations that do not link rustc, e.g., editors.
+// Each request is a JSON object in one line:
+//     {"id": 1, "method": "is_connected", "params": {"a": 1, "b": 2}}
+// and it is answered by one line with either the result or the error:
+//     {"id": 1, "result": true}
+//     {"id": 1, "error": "unknown method: .."}
+// The supported methods are:
+//     is_connected {"a": local, "b": local} -> bool
+//     slice {"local": local, "direction": "upside" | "downside" | "both"} -> [local], in dfs preorder
+//     provenance {"local": local} -> [local], the roots (see `Graph::roots`) that the local depends on
+pub fn dispatch(graph: &Graph, request: &str) -> String {
+    respond(request, |_| Ok(graph))
+}
+
+// Same as `dispatch`, but the graph is looked up by the def path of the function named in the request, e.g.,
+//     {"id": 1, "function": "foo::bar", "method": "is_connected", "params": {"a": 1, "b": 2}}
+pub fn dispatch_in(graphs: &HashMap<String, Graph>, request: &str) -> String {
+    respond(request, |request| {
+        let Some(function) = request.get("function").and_then(Value::as_str) else {
+            return Err(String::from("missing function"));
+        };
+        graphs
+            .get(function)
+            .ok_or_else(|| format!("no such function: {}", function))
+    })
+}
+
+// Answer the requests of `dispatch_in` line by line until the input ends, e.g., over the stdio of an editor.
+// The empty lines are skipped, and each answer is flushed at once so that the requests can be interleaved.
+pub fn serve(
+    graphs: &HashMap<String, Graph>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(output, "{}", dispatch_in(graphs, &line))?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn respond<'a, F>(request: &str, graph_of: F) -> String
+where
+    F: FnOnce(&Value) -> Result<&'a Graph, String>,
+{
+    let request: Value = match serde_json::from_str(request) {
+        Ok(request) => request,
+        Err(err) => return json!({"id": Value::Null, "error": err.to_string()}).to_string(),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    match graph_of(&request).and_then(|graph| answer(graph, &request)) {
+        Ok(result) => json!({"id": id, "result": result}).to_string(),
+        Err(err) => json!({"id": id, "error": err}).to_string(),
+    }
+}
+
+fn answer(graph: &Graph, request: &Value) -> Result<Value, String> {
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    match request.get("method").and_then(Value::as_str) {
+        Some("is_connected") => get_local(graph, &params, "a")
+            .and_then(|a| get_local(graph, &params, "b").map(|b| json!(graph.is_connected(a, b)))),
+        Some("slice") => get_local(graph, &params, "local").and_then(|local| {
+            get_direction(&params).map(|direction| locals_to_json(dfs(graph, local, direction)))
+        }),
+        Some("provenance") => get_local(graph, &params, "local").map(|local| {
+            let upside: HashSet<Local> = dfs(graph, local, Direction::Upside).into_iter().collect();
+            locals_to_json(
+                graph
+                    .roots()
+                    .into_iter()
+                    .filter(|root| upside.contains(root))
+                    .collect(),
+            )
+        }),
+        Some(method) => Err(format!("unknown method: {}", method)),
+        None => Err(String::from("missing method")),
+    }
+}
+
+fn get_local(graph: &Graph, params: &Value, key: &str) -> Result<Local, String> {
+    let Some(idx) = params.get(key).and_then(Value::as_u64) else {
+        return Err(format!("missing param: {}", key));
+    };
+    if idx as usize >= graph.nodes.len() {
+        return Err(format!("no such local: {}", idx));
+    }
+    Ok(Local::from_usize(idx as usize))
+}
+
+fn get_direction(params: &Value) -> Result<Direction, String> {
+    match params.get("direction").and_then(Value::as_str) {
+        Some("upside") => Ok(Direction::Upside),
+        Some("downside") => Ok(Direction::Downside),
+        Some("both") => Ok(Direction::Both),
+        Some(direction) => Err(format!("unknown direction: {}", direction)),
+        None => Err(String::from("missing param: direction")),
+    }
+}
+
+fn locals_to_json(locals: Vec<Local>) -> Value {
+    json!(locals
+        .iter()
+        .map(|local| local.as_usize())
+        .collect::<Vec<usize>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::core::dataflow::graph::EdgeOp;
+    use rustc_hir::def_id::CRATE_DEF_ID;
+    use rustc_span::DUMMY_SP;
+
+    #[test]
+    fn test_dispatch_one_request_per_method() {
+        // _2 = copy _1; _0 = move _2; _3 = const 5;
+        let mut graph = Graph::new(CRATE_DEF_ID.to_def_id(), DUMMY_SP, 1, 4);
+        graph.add_node_edge(Local::from_usize(1), Local::from_usize(2), EdgeOp::Copy);
+        graph.add_node_edge(Local::from_usize(2), Local::from_usize(0), EdgeOp::Move);
+        graph.add_const_edge(5, DUMMY_SP, Local::from_usize(3), EdgeOp::Const);
+        let requests = [
+            (
+                r#"{"id": 1, "method": "is_connected", "params": {"a": 1, "b": 0}}"#,
+                r#"{"id":1,"result":true}"#,
+            ),
+            (
+                r#"{"id": 2, "method": "is_connected", "params": {"a": 3, "b": 0}}"#,
+                r#"{"id":2,"result":false}"#,
+            ),
+            (
+                r#"{"id": 3, "method": "slice", "params": {"local": 2, "direction": "upside"}}"#,
+                r#"{"id":3,"result":[2,1]}"#,
+            ),
+            (
+                r#"{"id": 4, "method": "provenance", "params": {"local": 0}}"#,
+                r#"{"id":4,"result":[1]}"#,
+            ),
+            (
+                r#"{"id": 5, "method": "slice", "params": {"local": 9, "direction": "both"}}"#,
+                r#"{"id":5,"error":"no such local: 9"}"#,
+            ),
+        ];
+        for (request, response) in requests {
+            let answer: Value = serde_json::from_str(&dispatch(&graph, request)).unwrap();
+            assert_eq!(answer, serde_json::from_str::<Value>(response).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_serve_two_requests_in_one_session() {
+        // one::f: _0 = copy _1; two::g: _0 = const 5;
+        let mut one = Graph::new(CRATE_DEF_ID.to_def_id(), DUMMY_SP, 1, 2);
+        one.add_node_edge(Local::from_usize(1), Local::from_usize(0), EdgeOp::Copy);
+        let mut two = Graph::new(CRATE_DEF_ID.to_def_id(), DUMMY_SP, 1, 2);
+        two.add_const_edge(5, DUMMY_SP, Local::from_usize(0), EdgeOp::Const);
+        let graphs = HashMap::from([(String::from("one::f"), one), (String::from("two::g"), two)]);
+        let input = concat!(
+            r#"{"id": 1, "function": "one::f", "method": "is_connected", "params": {"a": 1, "b": 0}}"#,
+            "\n\n",
+            r#"{"id": 2, "function": "two::g", "method": "is_connected", "params": {"a": 1, "b": 0}}"#,
+            "\n",
+            r#"{"id": 3, "function": "three::h", "method": "slice"}"#,
+            "\n",
+        );
+        let mut output = vec![];
+        serve(&graphs, input.as_bytes(), &mut output).unwrap();
+        let answers: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            answers,
+            vec![
+                json!({"id": 1, "result": true}),
+                json!({"id": 2, "result": false}),
+                json!({"id": 3, "error": "no such function: three::h"}),
+            ]
+        );
+    }
+}
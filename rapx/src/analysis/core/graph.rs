@@ -0,0 +1,351 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// The generic graph algorithms here only depend on the structure of nodes and edges,
+// so that they can be shared by the dataflow graph, the call graph, etc. without rustc.
+// Only `core` and `alloc` are used, so the module can be moved into a `no_std` crate as is.
+
+#[derive(Clone, Copy)]
+pub enum Direction {
+    Upside,
+    Downside,
+    Both,
+}
+
+pub enum DFSStatus {
+    Continue, // true
+    Stop,     // false
+}
+
+impl DFSStatus {
+    pub fn and(s1: DFSStatus, s2: DFSStatus) -> DFSStatus {
+        if matches!(s1, DFSStatus::Stop) || matches!(s2, DFSStatus::Stop) {
+            DFSStatus::Stop
+        } else {
+            DFSStatus::Continue
+        }
+    }
+
+    pub fn or(s1: DFSStatus, s2: DFSStatus) -> DFSStatus {
+        if matches!(s1, DFSStatus::Continue) || matches!(s2, DFSStatus::Continue) {
+            DFSStatus::Continue
+        } else {
+            DFSStatus::Stop
+        }
+    }
+}
+
+pub trait DirectedGraph {
+    type Node: Copy + Ord;
+    type Edge: Copy;
+
+    // all the nodes in a deterministic order
+    fn nodes(&self) -> Vec<Self::Node>;
+    // the edges into (Upside) or out of (Downside) the node, each with the node at its other end,
+    // where the edges into the node go first for `Direction::Both`
+    fn edges(&self, node: Self::Node, direction: Direction) -> Vec<(Self::Edge, Self::Node)>;
+
+    fn neighbors_in(&self, node: Self::Node, direction: Direction) -> Vec<Self::Node> {
+        self.edges(node, direction)
+            .into_iter()
+            .map(|(_, neighbor)| neighbor)
+            .collect()
+    }
+
+    fn successors(&self, node: Self::Node) -> Vec<Self::Node> {
+        self.neighbors_in(node, Direction::Downside)
+    }
+
+    fn predecessors(&self, node: Self::Node) -> Vec<Self::Node> {
+        self.neighbors_in(node, Direction::Upside)
+    }
+}
+
+// Collect the nodes reachable from the start (including itself) in depth-first preorder.
+pub fn dfs<G: DirectedGraph>(graph: &G, start: G::Node, direction: Direction) -> Vec<G::Node> {
+    let mut visited = BTreeSet::new();
+    let mut order = Vec::new();
+    let mut stack = vec![start];
+    while let Some(now) = stack.pop() {
+        if !visited.insert(now) {
+            continue;
+        }
+        order.push(now);
+        // push in reverse so that the first neighbor is visited first
+        for next in graph.neighbors_in(now, direction).into_iter().rev() {
+            if !visited.contains(&next) {
+                stack.push(next);
+            }
+        }
+    }
+    order
+}
+
+// The depth-first traversal driven by the callbacks: `node_operator` decides whether to go on from each node,
+// and `edge_validator` whether to follow each edge. The nodes are not deduplicated, i.e., a node is visited once
+// per path reaching it, so `node_operator` is expected to stop at the visited nodes on cyclic graphs.
+// Once a callback stops, the whole traversal stops unless `traverse_all` is set.
+pub fn dfs_with<G, F, V>(
+    graph: &G,
+    now: G::Node,
+    direction: Direction,
+    node_operator: &mut F,
+    edge_validator: &mut V,
+    traverse_all: bool,
+) -> DFSStatus
+where
+    G: DirectedGraph,
+    F: FnMut(&G, G::Node) -> DFSStatus,
+    V: FnMut(&G, G::Edge) -> DFSStatus,
+{
+    if matches!(node_operator(graph, now), DFSStatus::Stop) {
+        return DFSStatus::Stop;
+    }
+    for (edge, next) in graph.edges(now, direction) {
+        if matches!(edge_validator(graph, edge), DFSStatus::Continue) {
+            let result = dfs_with(
+                graph,
+                next,
+                direction,
+                node_operator,
+                edge_validator,
+                traverse_all,
+            );
+            if matches!(result, DFSStatus::Stop) && !traverse_all {
+                return DFSStatus::Stop;
+            }
+        }
+    }
+    DFSStatus::Continue
+}
+
+pub fn is_reachable<G: DirectedGraph>(graph: &G, from: G::Node, to: G::Node) -> bool {
+    dfs(graph, from, Direction::Downside).contains(&to)
+}
+
+// The state of Tarjan's algorithm, where `call_stack` emulates the recursion with
+// (node, its successors not visited yet) pairs.
+struct Tarjan<N> {
+    index: BTreeMap<N, usize>,
+    low_link: BTreeMap<N, usize>,
+    on_stack: BTreeSet<N>,
+    stack: Vec<N>,
+    call_stack: Vec<(N, Vec<N>)>,
+}
+
+impl<N: Copy + Ord> Tarjan<N> {
+    fn visit<G: DirectedGraph<Node = N>>(&mut self, graph: &G, node: N) {
+        let next_index = self.index.len();
+        self.index.insert(node, next_index);
+        self.low_link.insert(node, next_index);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+        let mut successors = graph.successors(node);
+        successors.reverse();
+        self.call_stack.push((node, successors));
+    }
+}
+
+// Compute the strongly connected components by Tarjan's algorithm (without recursion).
+// Every node belongs to exactly one component, and the components are in reverse topological order,
+// i.e., a component is emitted before the components reaching it.
+pub fn strongly_connected_components<G: DirectedGraph>(graph: &G) -> Vec<Vec<G::Node>> {
+    let mut tarjan = Tarjan {
+        index: BTreeMap::new(),
+        low_link: BTreeMap::new(),
+        on_stack: BTreeSet::new(),
+        stack: Vec::new(),
+        call_stack: Vec::new(),
+    };
+    let mut components: Vec<Vec<G::Node>> = Vec::new();
+    for root in graph.nodes() {
+        if tarjan.index.contains_key(&root) {
+            continue;
+        }
+        tarjan.visit(graph, root);
+        while let Some((node, successors)) = tarjan.call_stack.last_mut() {
+            let node = *node;
+            if let Some(next) = successors.pop() {
+                if !tarjan.index.contains_key(&next) {
+                    tarjan.visit(graph, next);
+                } else if tarjan.on_stack.contains(&next) {
+                    let low = tarjan.low_link[&node].min(tarjan.index[&next]);
+                    tarjan.low_link.insert(node, low);
+                }
+                continue;
+            }
+            tarjan.call_stack.pop();
+            if let Some((caller, _)) = tarjan.call_stack.last() {
+                let low = tarjan.low_link[caller].min(tarjan.low_link[&node]);
+                tarjan.low_link.insert(*caller, low);
+            }
+            if tarjan.low_link[&node] == tarjan.index[&node] {
+                let mut component = Vec::new();
+                while let Some(member) = tarjan.stack.pop() {
+                    tarjan.on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the nodes are 0..n_nodes, with the edges as (src, dst) pairs
+    struct ToyGraph {
+        n_nodes: usize,
+        edges: Vec<(usize, usize)>,
+    }
+
+    impl DirectedGraph for ToyGraph {
+        type Node = usize;
+        type Edge = usize; //the index in `edges`
+
+        fn nodes(&self) -> Vec<usize> {
+            (0..self.n_nodes).collect()
+        }
+
+        fn edges(&self, node: usize, direction: Direction) -> Vec<(usize, usize)> {
+            let in_edges = self
+                .edges
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, dst))| *dst == node)
+                .map(|(idx, (src, _))| (idx, *src));
+            let out_edges = self
+                .edges
+                .iter()
+                .enumerate()
+                .filter(|(_, (src, _))| *src == node)
+                .map(|(idx, (_, dst))| (idx, *dst));
+            match direction {
+                Direction::Upside => in_edges.collect(),
+                Direction::Downside => out_edges.collect(),
+                Direction::Both => in_edges.chain(out_edges).collect(),
+            }
+        }
+    }
+
+    fn sorted_components(graph: &ToyGraph) -> Vec<Vec<usize>> {
+        strongly_connected_components(graph)
+            .into_iter()
+            .map(|mut component| {
+                component.sort();
+                component
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_self_loop() {
+        let graph = ToyGraph {
+            n_nodes: 2,
+            edges: vec![(0, 0), (0, 1)],
+        };
+        assert_eq!(dfs(&graph, 0, Direction::Downside), vec![0, 1]);
+        assert!(is_reachable(&graph, 0, 0));
+        assert!(!is_reachable(&graph, 1, 0));
+        assert_eq!(sorted_components(&graph), vec![vec![1], vec![0]]);
+    }
+
+    #[test]
+    fn test_two_components() {
+        // 0 <-> 1 -> 2 <-> 3
+        let graph = ToyGraph {
+            n_nodes: 4,
+            edges: vec![(0, 1), (1, 0), (1, 2), (2, 3), (3, 2)],
+        };
+        // the component of 2 and 3 is emitted first, since it is reached from the other one
+        assert_eq!(sorted_components(&graph), vec![vec![2, 3], vec![0, 1]]);
+        assert!(is_reachable(&graph, 0, 3));
+        assert!(!is_reachable(&graph, 3, 0));
+    }
+
+    #[test]
+    fn test_reverse_direction() {
+        // 0 -> 1 -> 2, 3 -> 2
+        let graph = ToyGraph {
+            n_nodes: 4,
+            edges: vec![(0, 1), (1, 2), (3, 2)],
+        };
+        assert_eq!(dfs(&graph, 2, Direction::Upside), vec![2, 1, 0, 3]);
+        assert_eq!(dfs(&graph, 2, Direction::Downside), vec![2]);
+        assert_eq!(dfs(&graph, 1, Direction::Both), vec![1, 0, 2, 3]);
+    }
+
+    #[test]
+    fn test_dag() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3
+        let graph = ToyGraph {
+            n_nodes: 4,
+            edges: vec![(0, 1), (0, 2), (1, 3), (2, 3)],
+        };
+        assert_eq!(dfs(&graph, 0, Direction::Downside), vec![0, 1, 3, 2]);
+        // every node is a component by itself, in reverse topological order
+        let components = sorted_components(&graph);
+        assert_eq!(components.len(), 4);
+        let position = |node: usize| components.iter().position(|c| c == &vec![node]).unwrap();
+        for (src, dst) in graph.edges.iter() {
+            assert!(position(*dst) < position(*src));
+        }
+    }
+
+    #[test]
+    fn test_dfs_with_callbacks() {
+        // 0 -> 1 -> 2, 0 -> 2, where the edge 1 -> 2 is filtered out
+        let graph = ToyGraph {
+            n_nodes: 3,
+            edges: vec![(0, 1), (1, 2), (0, 2)],
+        };
+        let mut order = vec![];
+        let mut node_operator = |_: &ToyGraph, node: usize| {
+            order.push(node);
+            DFSStatus::Continue
+        };
+        let mut edge_validator = |_: &ToyGraph, edge: usize| {
+            if edge == 1 {
+                DFSStatus::Stop
+            } else {
+                DFSStatus::Continue
+            }
+        };
+        dfs_with(
+            &graph,
+            0,
+            Direction::Downside,
+            &mut node_operator,
+            &mut edge_validator,
+            false,
+        );
+        assert_eq!(order, vec![0, 1, 2]);
+        // stopping at a node stops the whole traversal
+        let mut order = vec![];
+        let mut node_operator = |_: &ToyGraph, node: usize| {
+            order.push(node);
+            if node == 1 {
+                DFSStatus::Stop
+            } else {
+                DFSStatus::Continue
+            }
+        };
+        let status = dfs_with(
+            &graph,
+            0,
+            Direction::Downside,
+            &mut node_operator,
+            &mut |_: &ToyGraph, _: usize| DFSStatus::Continue,
+            false,
+        );
+        assert!(matches!(status, DFSStatus::Stop));
+        assert_eq!(order, vec![0, 1]);
+    }
+}
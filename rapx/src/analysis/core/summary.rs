@@ -0,0 +1,74 @@
+use rustc_hir::def_id::DefId;
+use rustc_index::IndexVec;
+use rustc_middle::mir::{Body, Local};
+use rustc_middle::ty::TyCtxt;
+
+use crate::analysis::core::call_graph::call_graph_helper::CallGraphInfo;
+use crate::analysis::core::call_graph::call_graph_visitor::CallGraphVisitor;
+use crate::analysis::core::dataflow::DataFlow;
+
+// The high-level facts of a function, bundled from the call graph and the dataflow graph.
+#[derive(Clone, Debug)]
+pub struct FunctionSummary {
+    pub def_path: String,
+    pub argc: usize,
+    pub param_return_deps: IndexVec<Local, bool>, //see `Graph::param_return_deps`
+    pub direct_callees: Vec<String>, //the def paths of the callees resolved by the call graph
+    pub has_side_effects: bool,
+    pub allocation_site_count: usize,
+}
+
+// Run both the call graph and the dataflow analyses on the function once.
+// The mir of the function must be available.
+pub fn summarize(tcx: TyCtxt<'_>, def_id: DefId) -> FunctionSummary {
+    let def_path = tcx.def_path_str(def_id);
+    let graph = DataFlow::new(tcx, false).build_graph(def_id);
+
+    let body: &Body = tcx.optimized_mir(def_id);
+    let mut call_graph_info = CallGraphInfo::new();
+    CallGraphVisitor::new(tcx, def_id, body, &mut call_graph_info).visit();
+    let mut direct_callees: Vec<String> = call_graph_info
+        .get_callees_path(&def_path)
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    direct_callees.sort();
+
+    FunctionSummary {
+        def_path,
+        argc: graph.argc,
+        param_return_deps: graph.param_return_deps(),
+        direct_callees,
+        has_side_effects: graph.has_side_effect_ops(),
+        allocation_site_count: graph.allocation_sites().len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{def_id_by_name, with_tcx};
+
+    #[test]
+    fn test_summarize_forwarding_call() {
+        let source = r#"
+            fn helper(x: u32) -> u32 {
+                x
+            }
+            pub fn forward(a: u32, _b: u32) -> u32 {
+                helper(a)
+            }
+        "#;
+        with_tcx("summarize", source, |tcx| {
+            let summary = summarize(tcx, def_id_by_name(tcx, "forward"));
+            assert_eq!(summary.def_path, "forward");
+            assert_eq!(summary.argc, 2);
+            // `_0` itself, then `a` and `_b`
+            assert_eq!(summary.param_return_deps.raw, vec![true, true, false]);
+            assert_eq!(summary.direct_callees, vec![String::from("helper")]);
+            // the call may have side effects
+            assert!(summary.has_side_effects);
+            assert_eq!(summary.allocation_site_count, 0);
+        });
+    }
+}
@@ -16,6 +16,8 @@ Analysis:
     -adg            generate API dependency graphs
     -callgraph      generate callgraphs
     -dataflow       (not supported yet) generate dataflow graphs
+    -dataflow=serve answer the dataflow queries in line-delimited json over stdio
+                    (run the rapx driver directly, as cargo does not forward stdin)
     -heap           analyze if the type holds a piece of memory on heap
     -audit          (under development) generate unsafe code audit units
 
@@ -5,6 +5,7 @@
 pub mod utils;
 pub mod analysis;
 
+extern crate alloc;
 extern crate rustc_data_structures;
 extern crate rustc_driver;
 extern crate rustc_errors;
@@ -256,6 +257,7 @@ pub fn start_analyzer(tcx: TyCtxt, callback: RapCallback) {
     match callback.is_dataflow_enabled() {
         1 => DataFlow::new(tcx, false).start(),
         2 => DataFlow::new(tcx, true).start(),
+        3 => DataFlow::new(tcx, false).serve(),
         _ => {}
     }
 
@@ -1,3 +1,5 @@
 pub mod fs;
 pub mod log;
 pub mod source;
+#[cfg(test)]
+pub mod test_utils;
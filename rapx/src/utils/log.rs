@@ -132,6 +132,14 @@ pub fn span_to_line_number(span: Span) -> usize {
     get_source_map().unwrap().lookup_char_pos(span.lo()).line
 }
 
+#[inline]
+// e.g., src/main.rs:2:9: 2:10
+pub fn span_to_string(span: Span) -> String {
+    get_source_map()
+        .unwrap()
+        .span_to_string(span, FileNameDisplayPreference::Local)
+}
+
 #[inline]
 // this function computes the relative pos range of two spans which could be generated from two dirrerent files or not intersect with each other
 // warning: we just return 0..0 to drop off the unintersected pairs
@@ -0,0 +1,101 @@
+// The helpers for the unit tests which need a `TyCtxt`, by compiling a small crate in place.
+use std::sync::{Mutex, Once};
+
+use log::{Level, Log, Metadata, Record};
+use rustc_driver::{Callbacks, Compilation, RunCompiler};
+use rustc_hir::def_id::DefId;
+use rustc_interface::{interface::Compiler, Queries};
+use rustc_middle::ty::TyCtxt;
+
+struct TestCallbacks<F> {
+    analysis: Option<F>,
+}
+
+impl<F: for<'tcx> FnOnce(TyCtxt<'tcx>) + Send> Callbacks for TestCallbacks<F> {
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        let analysis = self.analysis.take().unwrap();
+        queries.global_ctxt().unwrap().enter(analysis);
+        Compilation::Stop
+    }
+}
+
+// Compile the source as a library crate named after the test, and run the analysis on its `TyCtxt`.
+pub fn with_tcx<F>(name: &str, source: &str, analysis: F)
+where
+    F: for<'tcx> FnOnce(TyCtxt<'tcx>) + Send,
+{
+    let dir = std::env::temp_dir().join(format!("rapx-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lib.rs");
+    std::fs::write(&path, source).unwrap();
+    let mut args: Vec<String> = vec![
+        "rustc".to_owned(),
+        path.to_string_lossy().into_owned(),
+        format!("--crate-name={}", name),
+        "--crate-type=lib".to_owned(),
+        "--edition=2021".to_owned(),
+        // only checked as `cargo rapx` does, so that the mir before the optimizations is still available
+        "--emit=metadata".to_owned(),
+        format!("--out-dir={}", dir.to_string_lossy()),
+    ];
+    args.extend(crate::RAP_DEFAULT_ARGS.iter().map(ToString::to_string));
+    let mut callbacks = TestCallbacks {
+        analysis: Some(analysis),
+    };
+    let result = RunCompiler::new(&args, &mut callbacks).run();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok() && callbacks.analysis.is_none());
+}
+
+// the local def id of the item with the name, which should be unique in the crate
+pub fn def_id_by_name(tcx: TyCtxt<'_>, name: &str) -> DefId {
+    tcx.iter_local_def_id()
+        .find(|local_def_id| {
+            tcx.opt_item_name(local_def_id.to_def_id())
+                .is_some_and(|symbol| symbol.as_str() == name)
+        })
+        .unwrap()
+        .to_def_id()
+}
+
+// A logger keeping the records of the `RAP` target instead of printing them.
+// It is shared by all the tests in the process, so the records of other tests may show up as well.
+struct CaptureLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "RAP"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let message = record.args().to_string();
+            self.records.lock().unwrap().push((record.level(), message));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+// install the capturing logger once, then the records logged so far can be read by `captured_logs`
+pub fn capture_logs() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+}
+
+pub fn captured_logs() -> Vec<(Level, String)> {
+    LOGGER.records.lock().unwrap().clone()
+}
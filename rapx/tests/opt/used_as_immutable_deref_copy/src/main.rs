@@ -0,0 +1,9 @@
+// `(**v)` derefs the box through `deref_copy (*_1)`
+fn consume(v: &Box<Vec<i32>>) {
+    let c = (**v).clone();
+    drop(c);
+}
+
+fn main() {
+    consume(&Box::new(vec![1, 2, 3]));
+}
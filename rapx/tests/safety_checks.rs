@@ -16,7 +16,9 @@ fn running_tests_with_arg(dir: &str, arg: &str) -> String {
     // let stderr = String::from_utf8_lossy(&output.stderr);
     // println!("STDERR:\n{}", stderr);
 
-    let s = String::from_utf8_lossy(&output.stderr).into_owned();
+    // the reports rendered by annotate_snippets are forwarded to the stdout by cargo
+    let mut s = String::from_utf8_lossy(&output.stderr).into_owned();
+    s.push_str(&String::from_utf8_lossy(&output.stdout));
     s
 }
 
@@ -233,3 +235,12 @@ fn test_heap_proxy() {
         true
     );
 }
+
+#[test]
+fn test_opt_used_as_immutable_deref_copy() {
+    let output = running_tests_with_arg("opt/used_as_immutable_deref_copy", "-O");
+    assert_eq!(
+        output.contains("Unnecessary memory cloning detected"),
+        true
+    );
+}